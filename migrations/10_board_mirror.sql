@@ -0,0 +1,4 @@
+-- boards that mirror a group on another server, its posts arrive through the board's follow
+alter table "board"
+add column mirror_actor_id text unique,
+add column mirror_accepted boolean not null default false;
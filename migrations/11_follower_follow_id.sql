@@ -0,0 +1,3 @@
+-- the Follow activity a follower sent, an Undo may reference it by its id alone
+alter table "follower"
+add column follow_id text;
@@ -0,0 +1,7 @@
+-- a dead inbox is revived as soon as its host sends anything signed
+alter table "dead_inbox"
+add column host text generated always as (
+    lower(substring(inbox from '^[a-zA-Z][a-zA-Z0-9+.-]*://(?:[^/@]*@)?(\[[^]]*\]|[^/:?#]+)'))
+) stored;
+
+create index dead_inbox_host on "dead_inbox" (host);
@@ -0,0 +1,2 @@
+alter table "board"
+add column settings jsonb not null default '{}';
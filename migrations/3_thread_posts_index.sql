@@ -0,0 +1 @@
+create index thread_posts on "thread" using gin (posts jsonb_path_ops);
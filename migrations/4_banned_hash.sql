@@ -0,0 +1,6 @@
+create table
+    "banned_hash" (
+        hash text primary key,
+        reason text not null,
+        created_at timestamptz not null default now ()
+    );
@@ -0,0 +1,4 @@
+-- generated by the application the first time a board's actor is needed
+alter table "board"
+add column public_key text,
+add column private_key text;
@@ -0,0 +1,9 @@
+create table
+    "follower" (
+        board_id uuid not null references board (board_id) on delete cascade,
+        actor_id text not null,
+        inbox text not null,
+        shared_inbox text,
+        created_at timestamptz not null default now (),
+        primary key (board_id, actor_id)
+    );
@@ -0,0 +1,19 @@
+create table
+    "delivery" (
+        delivery_id uuid primary key default gen_random_uuid (),
+        board_id uuid not null references board (board_id) on delete cascade,
+        inbox text not null,
+        activity jsonb not null,
+        attempts integer not null default 0,
+        next_attempt_at timestamptz not null default now (),
+        created_at timestamptz not null default now ()
+    );
+
+create index delivery_next_attempt_at on "delivery" (next_attempt_at);
+
+-- inboxes that kept failing, nothing is queued for them anymore
+create table
+    "dead_inbox" (
+        inbox text primary key,
+        dead_since timestamptz not null default now ()
+    );
@@ -0,0 +1,8 @@
+-- `*.example.com` covers example.com and all of its subdomains
+create table
+    "domain_policy" (
+        domain text primary key,
+        policy text not null check (policy in ('block', 'reject_media', 'accept')),
+        reason text not null,
+        created_at timestamptz not null default now ()
+    );
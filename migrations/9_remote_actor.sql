@@ -0,0 +1,14 @@
+-- actors of other servers as last fetched, the key is what their requests are verified with
+create table
+    "remote_actor" (
+        actor_id text primary key,
+        inbox text not null,
+        shared_inbox text,
+        preferred_username text,
+        name text,
+        key_id text not null,
+        public_key_pem text not null,
+        fetched_at timestamptz not null
+    );
+
+create index remote_actor_key_id on "remote_actor" (key_id);
@@ -1,10 +1,61 @@
 use uuid::Uuid;
 
+mod board_keys;
 mod board_use_case;
 
 pub use board_use_case::{BoardError, BoardPersistence, BoardUseCase, board_use_case};
 
+#[derive(Clone)]
 pub struct Board {
     pub board_id: Uuid,
     pub name: String,
+    pub settings: BoardSettings,
+    pub mirror: Option<Mirror>,
+}
+
+#[derive(Clone)]
+pub struct Mirror {
+    pub actor_id: String,
+    pub accepted: bool,
+}
+
+#[derive(Clone)]
+pub struct BoardKeys {
+    pub public_key_pem: String,
+    pub private_key_pem: String,
+}
+
+#[derive(Clone)]
+pub struct BoardSettings {
+    pub max_file_size: usize,
+    pub max_files_per_post: usize,
+    pub allowed_mime_types: Vec<String>,
+    pub keep_metadata: bool,
+    pub reject_duplicate_files: bool,
+    pub spoilers_enabled: bool,
+}
+
+/// Changes to a board's settings, `None` keeps what the board has.
+pub struct BoardSettingsUpdate {
+    pub max_file_size: Option<usize>,
+    pub max_files_per_post: Option<usize>,
+    pub allowed_mime_types: Option<Vec<String>>,
+    pub keep_metadata: Option<bool>,
+    pub reject_duplicate_files: Option<bool>,
+    pub spoilers_enabled: Option<bool>,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Self {
+            max_file_size: 4 * 1024 * 1024,
+            max_files_per_post: 1,
+            allowed_mime_types: ["image/jpeg", "image/png", "image/gif", "image/webp"]
+                .map(str::to_owned)
+                .to_vec(),
+            keep_metadata: false,
+            reject_duplicate_files: false,
+            spoilers_enabled: true,
+        }
+    }
 }
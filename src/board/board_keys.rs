@@ -0,0 +1,22 @@
+use rsa::{
+    RsaPrivateKey,
+    pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding},
+};
+
+use super::BoardKeys;
+
+const KEY_SIZE: usize = 2048;
+
+/// Creates a fresh RSA key pair, this takes a while and should not run on the async workers.
+pub(super) fn generate_board_keys() -> Option<BoardKeys> {
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_SIZE).ok()?;
+    let private_key_pem = private_key.to_pkcs8_pem(LineEnding::LF).ok()?;
+    let public_key_pem = private_key
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .ok()?;
+    Some(BoardKeys {
+        public_key_pem,
+        private_key_pem: private_key_pem.to_string(),
+    })
+}
@@ -1,8 +1,23 @@
-use super::Board;
+use uuid::Uuid;
+
+use super::board_keys::generate_board_keys;
+use super::{Board, BoardKeys, BoardSettings, BoardSettingsUpdate, Mirror};
+use crate::media::SUPPORTED_MIME_TYPES;
+
+/// Board names end up in urls and handles, so they are kept short and plain.
+const MAX_BOARD_NAME_LENGTH: usize = 32;
+
+/// Uploads are buffered in memory, so a post may hold at most this many files of this size.
+const MAX_FILE_SIZE_LIMIT: usize = 512 * 1024 * 1024;
+
+const MAX_FILES_PER_POST_LIMIT: usize = 16;
 
 pub enum BoardError {
     NotFound,
     DbError,
+    InvalidName,
+    AlreadyExists,
+    InvalidSettings(&'static str),
 }
 
 pub trait BoardPersistence {
@@ -12,6 +27,37 @@ pub trait BoardPersistence {
     ) -> impl Future<Output = Result<Board, BoardError>> + Send;
 
     fn find_all_boards(&self) -> impl Future<Output = Result<Vec<Board>, BoardError>> + Send;
+
+    fn find_board_keys(
+        &self,
+        board_id: Uuid,
+    ) -> impl Future<Output = Result<Option<BoardKeys>, BoardError>> + Send;
+
+    /// Stores the keys unless the board already has some, returning the ones that are kept.
+    fn insert_board_keys(
+        &self,
+        board_id: Uuid,
+        keys: &BoardKeys,
+    ) -> impl Future<Output = Result<BoardKeys, BoardError>> + Send;
+
+    fn insert_board(
+        &self,
+        board_name: &str,
+        mirror: Option<&Mirror>,
+    ) -> impl Future<Output = Result<Board, BoardError>> + Send;
+
+    fn delete_board(&self, board_id: Uuid) -> impl Future<Output = Result<(), BoardError>> + Send;
+
+    fn update_mirror_accepted(
+        &self,
+        board_id: Uuid,
+    ) -> impl Future<Output = Result<(), BoardError>> + Send;
+
+    fn update_board_settings(
+        &self,
+        board_id: Uuid,
+        update: &BoardSettingsUpdate,
+    ) -> impl Future<Output = Result<Board, BoardError>> + Send;
 }
 
 pub trait BoardUseCase {
@@ -20,6 +66,31 @@ pub trait BoardUseCase {
         board_name: &str,
     ) -> impl Future<Output = Result<Board, BoardError>> + Send;
     fn get_all_boards(&self) -> impl Future<Output = Result<Vec<Board>, BoardError>> + Send;
+    /// Keys are generated the first time a board needs them.
+    fn get_board_keys(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<BoardKeys, BoardError>> + Send;
+
+    /// Creates a board for a remote group, the follow is up to the caller.
+    fn create_mirror_board(
+        &self,
+        board_name: &str,
+        actor_id: &str,
+    ) -> impl Future<Output = Result<Board, BoardError>> + Send;
+
+    fn delete_board(&self, board: Board) -> impl Future<Output = Result<(), BoardError>> + Send;
+
+    fn accept_mirror_follow(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<(), BoardError>> + Send;
+
+    fn update_board_settings(
+        &self,
+        board: &Board,
+        update: BoardSettingsUpdate,
+    ) -> impl Future<Output = Result<Board, BoardError>> + Send;
 }
 
 pub fn board_use_case(persistence: impl BoardPersistence + Sync) -> impl BoardUseCase {
@@ -41,4 +112,103 @@ impl<T: BoardPersistence + Sync> BoardUseCase for BoardUseCaseImpl<T> {
     async fn get_all_boards(&self) -> Result<Vec<Board>, BoardError> {
         self.persistence.find_all_boards().await
     }
+
+    async fn get_board_keys(&self, board: &Board) -> Result<BoardKeys, BoardError> {
+        if let Some(keys) = self.persistence.find_board_keys(board.board_id).await? {
+            return Ok(keys);
+        }
+        let generated = tokio::task::spawn_blocking(generate_board_keys)
+            .await
+            .ok()
+            .flatten()
+            .ok_or(BoardError::DbError)?;
+        // a concurrent request may have won the race, its keys are kept
+        self.persistence
+            .insert_board_keys(board.board_id, &generated)
+            .await
+    }
+
+    async fn create_mirror_board(
+        &self,
+        board_name: &str,
+        actor_id: &str,
+    ) -> Result<Board, BoardError> {
+        if !is_valid_board_name(board_name) {
+            return Err(BoardError::InvalidName);
+        }
+        let mirror = Mirror {
+            actor_id: actor_id.to_owned(),
+            accepted: false,
+        };
+        self.persistence
+            .insert_board(board_name, Some(&mirror))
+            .await
+    }
+
+    async fn delete_board(&self, board: Board) -> Result<(), BoardError> {
+        self.persistence.delete_board(board.board_id).await
+    }
+
+    async fn accept_mirror_follow(&self, board: &Board) -> Result<(), BoardError> {
+        self.persistence
+            .update_mirror_accepted(board.board_id)
+            .await
+    }
+
+    async fn update_board_settings(
+        &self,
+        board: &Board,
+        update: BoardSettingsUpdate,
+    ) -> Result<Board, BoardError> {
+        validate_settings(&apply_update(&board.settings, &update))?;
+        self.persistence
+            .update_board_settings(board.board_id, &update)
+            .await
+    }
+}
+
+fn apply_update(settings: &BoardSettings, update: &BoardSettingsUpdate) -> BoardSettings {
+    BoardSettings {
+        max_file_size: update.max_file_size.unwrap_or(settings.max_file_size),
+        max_files_per_post: update
+            .max_files_per_post
+            .unwrap_or(settings.max_files_per_post),
+        allowed_mime_types: update
+            .allowed_mime_types
+            .clone()
+            .unwrap_or(settings.allowed_mime_types.clone()),
+        keep_metadata: update.keep_metadata.unwrap_or(settings.keep_metadata),
+        reject_duplicate_files: update
+            .reject_duplicate_files
+            .unwrap_or(settings.reject_duplicate_files),
+        spoilers_enabled: update.spoilers_enabled.unwrap_or(settings.spoilers_enabled),
+    }
+}
+
+fn validate_settings(settings: &BoardSettings) -> Result<(), BoardError> {
+    if !(1..=MAX_FILE_SIZE_LIMIT).contains(&settings.max_file_size) {
+        return Err(BoardError::InvalidSettings(
+            "max_file_size must be between 1 byte and 512 MiB",
+        ));
+    }
+    if settings.max_files_per_post > MAX_FILES_PER_POST_LIMIT {
+        return Err(BoardError::InvalidSettings(
+            "max_files_per_post must be at most 16",
+        ));
+    }
+    let is_supported = |mime_type: &String| SUPPORTED_MIME_TYPES.contains(&mime_type.as_str());
+    if !settings.allowed_mime_types.iter().all(is_supported) {
+        return Err(BoardError::InvalidSettings(
+            "allowed_mime_types may only hold image/jpeg, image/png, image/gif, image/webp, \
+             video/webm, video/mp4 and audio/mpeg",
+        ));
+    }
+    Ok(())
+}
+
+fn is_valid_board_name(board_name: &str) -> bool {
+    (1..=MAX_BOARD_NAME_LENGTH).contains(&board_name.len())
+        && board_name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
 }
@@ -0,0 +1,50 @@
+mod delivery_use_case;
+mod federation_use_case;
+mod remote_actor_use_case;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+pub use delivery_use_case::{
+    DeadHosts, DeliveryError, DeliveryPersistence, DeliveryUseCase, delivery_use_case,
+};
+pub use federation_use_case::{
+    FederationError, FederationPersistence, FederationUseCase, Publisher, federation_use_case,
+};
+pub use remote_actor_use_case::{
+    RemoteActorError, RemoteActorPersistence, RemoteActorUseCase, is_stale, may_refetch,
+    remote_actor_use_case,
+};
+
+pub struct Follower {
+    pub actor_id: String,
+    pub inbox: String,
+    pub shared_inbox: Option<String>,
+    /// Id of the `Follow` activity, unknown for followers from before it was kept.
+    pub follow_id: Option<String>,
+}
+
+pub struct Delivery {
+    pub delivery_id: Uuid,
+    pub board_name: String,
+    pub inbox: String,
+    pub activity: String, // serialized json
+    pub attempts: i32,
+}
+
+pub struct DeadInbox {
+    pub inbox: String,
+    pub dead_since: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct RemoteActor {
+    pub actor_id: String,
+    pub inbox: String,
+    pub shared_inbox: Option<String>,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    pub key_id: String,
+    pub public_key_pem: String,
+    pub fetched_at: DateTime<Utc>,
+}
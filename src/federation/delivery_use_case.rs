@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use url::Url;
+use uuid::Uuid;
+
+use crate::board::Board;
+use crate::moderation::{ModerationPersistence, Policy, domain_policy_for};
+
+use super::{DeadInbox, Delivery};
+
+const MAX_DELIVERY_ATTEMPTS: i32 = 10;
+
+/// The first retry waits this long, every further one twice as long as the one before.
+const BASE_RETRY_DELAY_SECONDS: i64 = 30;
+
+const MAX_RETRY_DELAY_SECONDS: i64 = 6 * 60 * 60;
+
+/// Claimed deliveries are left alone by other workers for this long.
+const CLAIM_LEASE_SECONDS: i64 = 5 * 60;
+
+/// Other processes may mark or revive hosts, so the cached set is reloaded this often.
+const DEAD_HOSTS_TTL_SECONDS: i64 = 60;
+
+pub enum DeliveryError {
+    DbError,
+}
+
+/// The hosts that have dead inboxes, shared by every delivery use case of the process.
+#[derive(Clone, Default)]
+pub struct DeadHosts(Arc<Mutex<Option<LoadedHosts>>>);
+
+struct LoadedHosts {
+    loaded_at: DateTime<Utc>,
+    hosts: HashSet<String>,
+}
+
+impl DeadHosts {
+    /// `None` until loaded and once it is older than [DEAD_HOSTS_TTL_SECONDS].
+    fn contains(&self, host: &str) -> Option<bool> {
+        let cache = self.0.lock().ok()?;
+        let loaded = cache.as_ref()?;
+        let fresh = Utc::now() - loaded.loaded_at < TimeDelta::seconds(DEAD_HOSTS_TTL_SECONDS);
+        fresh.then(|| loaded.hosts.contains(host))
+    }
+
+    fn load(&self, hosts: HashSet<String>) {
+        if let Ok(mut cache) = self.0.lock() {
+            *cache = Some(LoadedHosts {
+                loaded_at: Utc::now(),
+                hosts,
+            });
+        }
+    }
+
+    fn update(&self, host: &str, dead: bool) {
+        if let Ok(mut cache) = self.0.lock()
+            && let Some(loaded) = cache.as_mut()
+        {
+            match dead {
+                true => loaded.hosts.insert(host.to_owned()),
+                false => loaded.hosts.remove(host),
+            };
+        }
+    }
+}
+
+pub trait DeliveryPersistence {
+    /// Queues the activity for every inbox that is not dead.
+    fn insert_deliveries(
+        &self,
+        board: &Board,
+        inboxes: &[String],
+        activity: &str,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Returns due deliveries and moves their next attempt to `leased_until`.
+    fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        leased_until: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<Delivery>, DeliveryError>> + Send;
+
+    fn delete_delivery(
+        &self,
+        delivery_id: &Uuid,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    fn reschedule_delivery(
+        &self,
+        delivery_id: &Uuid,
+        attempts: i32,
+        next_attempt_at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Marks the inbox dead and drops everything still queued for it.
+    fn insert_dead_inbox(
+        &self,
+        inbox: &str,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    fn find_dead_inboxes(
+        &self,
+    ) -> impl Future<Output = Result<Vec<DeadInbox>, DeliveryError>> + Send;
+
+    fn find_dead_hosts(&self) -> impl Future<Output = Result<Vec<String>, DeliveryError>> + Send;
+
+    fn delete_dead_inboxes(
+        &self,
+        host: &str,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+}
+
+pub trait DeliveryUseCase {
+    /// Inboxes shared by several followers get the activity once, blocked domains get nothing.
+    fn enqueue(
+        &self,
+        board: &Board,
+        inboxes: Vec<String>,
+        activity: &str,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Deliveries to domains blocked since they were queued are dropped instead.
+    fn claim_due_deliveries(
+        &self,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Delivery>, DeliveryError>> + Send;
+
+    fn delivered(
+        &self,
+        delivery: &Delivery,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    fn failed(&self, delivery: &Delivery)
+    -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Tries again later without counting an attempt, for faults on our side.
+    fn postpone(
+        &self,
+        delivery: &Delivery,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Drops a delivery that can never be sent, without holding it against the inbox.
+    fn discard(
+        &self,
+        delivery: &Delivery,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    fn get_dead_inboxes(
+        &self,
+    ) -> impl Future<Output = Result<Vec<DeadInbox>, DeliveryError>> + Send;
+
+    fn revive_host(&self, host: &str) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+
+    /// Checks the cached dead hosts first, it runs for every signed request.
+    fn revive_dead_host(
+        &self,
+        host: &str,
+    ) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+}
+
+pub fn delivery_use_case(
+    persistence: impl DeliveryPersistence + Sync,
+    moderation_persistence: impl ModerationPersistence + Sync,
+    dead_hosts: DeadHosts,
+) -> impl DeliveryUseCase {
+    DeliveryUseCaseImpl {
+        persistence,
+        moderation_persistence,
+        dead_hosts,
+    }
+}
+
+struct DeliveryUseCaseImpl<T, M>
+where
+    T: DeliveryPersistence,
+    M: ModerationPersistence,
+{
+    persistence: T,
+    moderation_persistence: M,
+    dead_hosts: DeadHosts,
+}
+
+impl<T: DeliveryPersistence + Sync, M: ModerationPersistence + Sync> DeliveryUseCaseImpl<T, M> {
+    async fn is_blocked(&self, inbox: &str) -> Result<bool, DeliveryError> {
+        let Some(host) = Url::parse(inbox)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+        else {
+            return Ok(false); // fails on delivery, like any unreachable inbox
+        };
+        match domain_policy_for(&self.moderation_persistence, &host).await {
+            Ok(policy) => Ok(policy == Policy::Block),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+}
+
+impl<T: DeliveryPersistence + Sync, M: ModerationPersistence + Sync> DeliveryUseCase
+    for DeliveryUseCaseImpl<T, M>
+{
+    async fn enqueue(
+        &self,
+        board: &Board,
+        mut inboxes: Vec<String>,
+        activity: &str,
+    ) -> Result<(), DeliveryError> {
+        inboxes.sort();
+        inboxes.dedup();
+        let mut allowed = Vec::with_capacity(inboxes.len());
+        for inbox in inboxes {
+            if !self.is_blocked(&inbox).await? {
+                allowed.push(inbox);
+            }
+        }
+        let inboxes = allowed;
+        if inboxes.is_empty() {
+            return Ok(());
+        }
+        self.persistence
+            .insert_deliveries(board, &inboxes, activity)
+            .await
+    }
+
+    async fn claim_due_deliveries(&self, limit: i64) -> Result<Vec<Delivery>, DeliveryError> {
+        let leased_until = Utc::now() + TimeDelta::seconds(CLAIM_LEASE_SECONDS);
+        let claimed = self
+            .persistence
+            .claim_due_deliveries(limit, leased_until)
+            .await?;
+        let mut deliveries = Vec::with_capacity(claimed.len());
+        for delivery in claimed {
+            if self.is_blocked(&delivery.inbox).await? {
+                self.persistence
+                    .delete_delivery(&delivery.delivery_id)
+                    .await?;
+            } else {
+                deliveries.push(delivery);
+            }
+        }
+        Ok(deliveries)
+    }
+
+    async fn delivered(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
+        self.persistence
+            .delete_delivery(&delivery.delivery_id)
+            .await
+    }
+
+    async fn failed(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
+        let attempts = delivery.attempts + 1;
+        if is_dead(attempts) {
+            self.persistence.insert_dead_inbox(&delivery.inbox).await?;
+            if let Some(host) = host_of(&delivery.inbox) {
+                self.dead_hosts.update(&host, true);
+            }
+            return Ok(());
+        }
+        let next_attempt_at = Utc::now() + retry_delay(attempts);
+        self.persistence
+            .reschedule_delivery(&delivery.delivery_id, attempts, next_attempt_at)
+            .await
+    }
+
+    async fn postpone(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
+        let next_attempt_at = Utc::now() + TimeDelta::seconds(BASE_RETRY_DELAY_SECONDS);
+        self.persistence
+            .reschedule_delivery(&delivery.delivery_id, delivery.attempts, next_attempt_at)
+            .await
+    }
+
+    async fn discard(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
+        self.persistence
+            .delete_delivery(&delivery.delivery_id)
+            .await
+    }
+
+    async fn get_dead_inboxes(&self) -> Result<Vec<DeadInbox>, DeliveryError> {
+        self.persistence.find_dead_inboxes().await
+    }
+
+    async fn revive_host(&self, host: &str) -> Result<(), DeliveryError> {
+        let host = host.to_lowercase();
+        self.persistence.delete_dead_inboxes(&host).await?;
+        self.dead_hosts.update(&host, false);
+        Ok(())
+    }
+
+    async fn revive_dead_host(&self, host: &str) -> Result<(), DeliveryError> {
+        let host = host.to_lowercase();
+        let is_dead = match self.dead_hosts.contains(&host) {
+            Some(is_dead) => is_dead,
+            None => {
+                let hosts: HashSet<String> = self
+                    .persistence
+                    .find_dead_hosts()
+                    .await?
+                    .into_iter()
+                    .collect();
+                let is_dead = hosts.contains(&host);
+                self.dead_hosts.load(hosts);
+                is_dead
+            }
+        };
+        if is_dead {
+            self.revive_host(&host).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lowercased like the `host` column of `dead_inbox`.
+fn host_of(inbox: &str) -> Option<String> {
+    let url = Url::parse(inbox).ok()?;
+    url.host_str().map(str::to_lowercase)
+}
+
+fn is_dead(attempts: i32) -> bool {
+    attempts >= MAX_DELIVERY_ATTEMPTS
+}
+
+fn retry_delay(attempts: i32) -> TimeDelta {
+    let factor = 1_i64 << (attempts - 1).clamp(0, 20);
+    TimeDelta::seconds((BASE_RETRY_DELAY_SECONDS * factor).min(MAX_RETRY_DELAY_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_retry_delay_up_to_the_cap() {
+        assert_eq!(retry_delay(1), TimeDelta::seconds(30));
+        assert_eq!(retry_delay(2), TimeDelta::seconds(60));
+        assert_eq!(retry_delay(9), TimeDelta::seconds(30 * 256));
+        assert_eq!(retry_delay(11), TimeDelta::hours(6));
+        assert_eq!(retry_delay(100), TimeDelta::hours(6));
+    }
+
+    #[test]
+    fn gives_up_on_the_tenth_failed_attempt() {
+        assert!(!is_dead(9));
+        assert!(is_dead(10));
+    }
+
+    #[test]
+    fn caches_dead_hosts_until_they_are_revived() {
+        let dead_hosts = DeadHosts::default();
+        assert_eq!(dead_hosts.contains("remote.example"), None);
+        dead_hosts.load(HashSet::from(["remote.example".to_owned()]));
+        assert_eq!(dead_hosts.contains("remote.example"), Some(true));
+        assert_eq!(dead_hosts.contains("other.example"), Some(false));
+        dead_hosts.update("remote.example", false);
+        dead_hosts.update("other.example", true);
+        assert_eq!(dead_hosts.contains("remote.example"), Some(false));
+        assert_eq!(dead_hosts.contains("other.example"), Some(true));
+    }
+}
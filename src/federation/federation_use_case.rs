@@ -0,0 +1,110 @@
+use crate::board::Board;
+use crate::thread::{Post, Thread};
+
+use super::Follower;
+
+pub enum FederationError {
+    DbError,
+}
+
+pub trait FederationPersistence {
+    fn upsert_follower(
+        &self,
+        board: &Board,
+        follower: &Follower,
+    ) -> impl Future<Output = Result<(), FederationError>> + Send;
+
+    /// With a `follow_id`, only a follower that followed with that activity is removed.
+    fn delete_follower(
+        &self,
+        board: &Board,
+        actor_id: &str,
+        follow_id: Option<&str>,
+    ) -> impl Future<Output = Result<(), FederationError>> + Send;
+
+    fn find_followers(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<Vec<Follower>, FederationError>> + Send;
+
+    fn count_followers(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<i64, FederationError>> + Send;
+}
+
+pub trait Publisher {
+    fn publish_thread(&self, board: &Board, thread: &Thread) -> impl Future<Output = ()> + Send;
+
+    fn publish_post(
+        &self,
+        board: &Board,
+        thread: &Thread,
+        post: &Post,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+pub trait FederationUseCase {
+    fn follow(
+        &self,
+        board: &Board,
+        follower: &Follower,
+    ) -> impl Future<Output = Result<(), FederationError>> + Send;
+
+    /// Unfollowing a board one does not follow is not an error. A `follow_id` has to match the
+    /// `Follow` the actor sent, an `Undo` that only references an id may be about anything.
+    fn unfollow(
+        &self,
+        board: &Board,
+        actor_id: &str,
+        follow_id: Option<&str>,
+    ) -> impl Future<Output = Result<(), FederationError>> + Send;
+
+    fn get_followers(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<Vec<Follower>, FederationError>> + Send;
+
+    fn count_followers(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<i64, FederationError>> + Send;
+}
+
+pub fn federation_use_case(
+    persistence: impl FederationPersistence + Sync,
+) -> impl FederationUseCase {
+    FederationUseCaseImpl { persistence }
+}
+
+struct FederationUseCaseImpl<T>
+where
+    T: FederationPersistence,
+{
+    persistence: T,
+}
+
+impl<T: FederationPersistence + Sync> FederationUseCase for FederationUseCaseImpl<T> {
+    async fn follow(&self, board: &Board, follower: &Follower) -> Result<(), FederationError> {
+        self.persistence.upsert_follower(board, follower).await
+    }
+
+    async fn unfollow(
+        &self,
+        board: &Board,
+        actor_id: &str,
+        follow_id: Option<&str>,
+    ) -> Result<(), FederationError> {
+        self.persistence
+            .delete_follower(board, actor_id, follow_id)
+            .await
+    }
+
+    async fn get_followers(&self, board: &Board) -> Result<Vec<Follower>, FederationError> {
+        self.persistence.find_followers(board).await
+    }
+
+    async fn count_followers(&self, board: &Board) -> Result<i64, FederationError> {
+        self.persistence.count_followers(board).await
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+use super::RemoteActor;
+
+/// Cached actors are fetched again once they are older than this.
+const ACTOR_TTL_HOURS: i64 = 24;
+
+/// A key that fails to verify is fetched again, but not more often than this.
+const MIN_REFETCH_SECONDS: i64 = 60;
+
+pub enum RemoteActorError {
+    NotFound,
+    DbError,
+}
+
+pub trait RemoteActorPersistence {
+    fn find_remote_actor(
+        &self,
+        actor_id: &str,
+    ) -> impl Future<Output = Result<RemoteActor, RemoteActorError>> + Send;
+
+    fn find_remote_actor_by_key(
+        &self,
+        key_id: &str,
+    ) -> impl Future<Output = Result<RemoteActor, RemoteActorError>> + Send;
+
+    fn upsert_remote_actor(
+        &self,
+        actor: &RemoteActor,
+    ) -> impl Future<Output = Result<(), RemoteActorError>> + Send;
+}
+
+pub trait RemoteActorUseCase {
+    fn get_actor(
+        &self,
+        actor_id: &str,
+    ) -> impl Future<Output = Result<RemoteActor, RemoteActorError>> + Send;
+
+    fn get_actor_by_key(
+        &self,
+        key_id: &str,
+    ) -> impl Future<Output = Result<RemoteActor, RemoteActorError>> + Send;
+
+    fn save_actor(
+        &self,
+        actor: &RemoteActor,
+    ) -> impl Future<Output = Result<(), RemoteActorError>> + Send;
+}
+
+pub fn remote_actor_use_case(
+    persistence: impl RemoteActorPersistence + Sync,
+) -> impl RemoteActorUseCase {
+    RemoteActorUseCaseImpl { persistence }
+}
+
+struct RemoteActorUseCaseImpl<T>
+where
+    T: RemoteActorPersistence,
+{
+    persistence: T,
+}
+
+impl<T: RemoteActorPersistence + Sync> RemoteActorUseCase for RemoteActorUseCaseImpl<T> {
+    async fn get_actor(&self, actor_id: &str) -> Result<RemoteActor, RemoteActorError> {
+        self.persistence.find_remote_actor(actor_id).await
+    }
+
+    async fn get_actor_by_key(&self, key_id: &str) -> Result<RemoteActor, RemoteActorError> {
+        self.persistence.find_remote_actor_by_key(key_id).await
+    }
+
+    async fn save_actor(&self, actor: &RemoteActor) -> Result<(), RemoteActorError> {
+        self.persistence.upsert_remote_actor(actor).await
+    }
+}
+
+/// Stale actors are fetched again before use, the stale copy only stands in if that fails.
+pub fn is_stale(actor: &RemoteActor, now: DateTime<Utc>) -> bool {
+    now - actor.fetched_at > TimeDelta::hours(ACTOR_TTL_HOURS)
+}
+
+/// Whether a key that did not verify may be fetched again, so a rotated key is picked up
+/// without letting bad signatures make us fetch on every request.
+pub fn may_refetch(actor: &RemoteActor, now: DateTime<Utc>) -> bool {
+    now - actor.fetched_at > TimeDelta::seconds(MIN_REFETCH_SECONDS)
+}
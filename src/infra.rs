@@ -1,16 +1,25 @@
+mod activitypub;
 mod db;
 mod dependency_injection;
 mod http;
+mod persistence;
 mod routing;
+mod storage;
 mod use_case_registry;
-mod persistence;
 
+pub use activitypub::FederationConfig;
+use activitypub::{actors::RemoteActorStore, client::ActivityPubClient};
 pub use dependency_injection::DepenencyInjector;
 pub use http::serve;
+use persistence::RemoteActorPgPersistence;
 
 #[derive(Clone)]
 pub struct AppState {
     pub port: String,
+    pub admin_token: Option<String>,
+    pub federation: FederationConfig,
+    pub remote_actors: RemoteActorStore,
+    pub activitypub: ActivityPubClient,
     pub di: DepenencyInjector,
 }
 
@@ -18,10 +27,26 @@ pub async fn create_app_state() -> AppState {
     let db_url =
         dotenvy::var("DATABASE_URL").expect("Env var DATABASE_URL is required for this service.");
     let port: String = dotenvy::var("PORT").unwrap_or("80".to_owned());
+    let admin_token: Option<String> = dotenvy::var("ADMIN_TOKEN").ok();
+    let federation = activitypub::init_federation_config(&port);
+    // remote urls may only point at public addresses unless explicitly allowed, e.g. for local testing
+    let allow_private_addresses =
+        dotenvy::var("ALLOW_PRIVATE_ADDRESSES").is_ok_and(|v| v == "true");
+    let activitypub = ActivityPubClient::new(allow_private_addresses);
     let db_pool = db::init_db_pool(db_url).await;
-    let use_case_registry = use_case_registry::build_registry(db_pool);
+    let remote_actors = RemoteActorStore::new(
+        activitypub.clone(),
+        RemoteActorPgPersistence::new(db_pool.clone()),
+    );
+    let media_backend = storage::init_media_backend();
+    let use_case_registry =
+        use_case_registry::build_registry(db_pool, media_backend, federation.clone());
     AppState {
         port,
+        admin_token,
+        federation,
+        remote_actors,
+        activitypub,
         di: DepenencyInjector { use_case_registry },
     }
 }
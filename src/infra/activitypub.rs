@@ -0,0 +1,149 @@
+pub mod actors;
+pub mod client;
+pub mod delivery_worker;
+pub mod objects;
+pub mod publisher;
+pub mod signature;
+
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    board::{BoardError, BoardUseCase},
+    infra::AppState,
+};
+use signature::Signer;
+
+/// Where this instance is reachable from the fediverse, taken from `PUBLIC_URL`.
+#[derive(Clone)]
+pub struct FederationConfig {
+    pub public_url: String,
+    /// Host that appears in `acct:` handles, see [FederationConfig::parse_handle].
+    pub domain: String,
+}
+
+/// A thread, or a post in it, referenced by its ActivityPub id.
+pub struct LocalObject {
+    pub board_name: String,
+    pub thread_id: Uuid,
+    pub post_id: Option<Uuid>,
+}
+
+impl FederationConfig {
+    pub fn board_actor_id(&self, board_name: &str) -> String {
+        format!("{}/boards/{}", self.public_url, board_name)
+    }
+
+    pub fn board_inbox(&self, board_name: &str) -> String {
+        format!("{}/inbox", self.board_actor_id(board_name))
+    }
+
+    pub fn board_outbox(&self, board_name: &str) -> String {
+        format!("{}/outbox", self.board_actor_id(board_name))
+    }
+
+    pub fn board_followers(&self, board_name: &str) -> String {
+        format!("{}/followers", self.board_actor_id(board_name))
+    }
+
+    /// Resolves `acct:{board_name}@{domain}` or a board actor id to the board name.
+    pub fn parse_handle<'a>(&self, resource: &'a str) -> Option<&'a str> {
+        let board_name = match resource.strip_prefix("acct:") {
+            Some(handle) => {
+                let (board_name, domain) = handle.rsplit_once('@')?;
+                if !domain.eq_ignore_ascii_case(&self.domain) {
+                    return None;
+                }
+                board_name
+            }
+            None => resource.strip_prefix(&format!("{}/boards/", self.public_url))?,
+        };
+        if board_name.is_empty() || board_name.contains('/') {
+            return None;
+        }
+        Some(board_name)
+    }
+
+    pub fn thread_object_id(&self, board_name: &str, thread_id: &Uuid) -> String {
+        format!("{}/threads/{}", self.board_actor_id(board_name), thread_id)
+    }
+
+    pub fn post_object_id(&self, board_name: &str, thread_id: &Uuid, post_id: &Uuid) -> String {
+        format!(
+            "{}/posts/{}",
+            self.thread_object_id(board_name, thread_id),
+            post_id
+        )
+    }
+
+    /// Resolves one of our thread or post ids to where the object lives.
+    pub fn parse_object_id(&self, object_id: &str) -> Option<LocalObject> {
+        let path = object_id.strip_prefix(&format!("{}/boards/", self.public_url))?;
+        let (board_name, path) = path.split_once("/threads/")?;
+        let (thread_id, post_id) = match path.split_once("/posts/") {
+            Some((thread_id, post_id)) => (thread_id, Some(Uuid::parse_str(post_id).ok()?)),
+            None => (path, None),
+        };
+        Some(LocalObject {
+            board_name: board_name.to_owned(),
+            thread_id: Uuid::parse_str(thread_id).ok()?,
+            post_id,
+        })
+    }
+
+    /// Media may be served by the api itself, remote servers need absolute urls.
+    pub fn absolute_url(&self, url: &str) -> String {
+        match url.starts_with('/') {
+            true => format!("{}{}", self.public_url, url),
+            false => url.to_owned(),
+        }
+    }
+
+    pub fn board_key_id(&self, board_name: &str) -> String {
+        format!("{}#main-key", self.board_actor_id(board_name))
+    }
+
+    /// Groups only take replies from people, the poster writes a mirrored board's local replies.
+    pub fn board_poster_id(&self, board_name: &str) -> String {
+        format!("{}/poster", self.board_actor_id(board_name))
+    }
+
+    pub fn board_poster_inbox(&self, board_name: &str) -> String {
+        format!("{}/inbox", self.board_poster_id(board_name))
+    }
+
+    pub fn board_poster_key_id(&self, board_name: &str) -> String {
+        format!("{}#main-key", self.board_poster_id(board_name))
+    }
+}
+
+pub async fn board_signer(app_state: &AppState, board_name: &str) -> Result<Signer, BoardError> {
+    let board_use_case = app_state.di.board_use_case();
+    let board = board_use_case.get_board_by_name(board_name).await?;
+    let keys = board_use_case.get_board_keys(&board).await?;
+    Ok(Signer {
+        key_id: app_state.federation.board_key_id(&board.name),
+        private_key_pem: keys.private_key_pem,
+    })
+}
+
+pub(crate) fn init_federation_config(port: &str) -> FederationConfig {
+    let public_url: String =
+        dotenvy::var("PUBLIC_URL").unwrap_or(format!("http://localhost:{}", port));
+    let parsed = Url::parse(&public_url).expect("PUBLIC_URL must be an absolute url");
+    // handles can live on another host than the app, e.g. the bare domain
+    let domain: String = match dotenvy::var("DOMAIN") {
+        Ok(domain) => domain,
+        Err(_) => {
+            let host = parsed.host_str().expect("PUBLIC_URL must have a host");
+            match parsed.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_owned(),
+            }
+        }
+    };
+    FederationConfig {
+        public_url: public_url.trim_end_matches('/').to_owned(),
+        domain,
+    }
+}
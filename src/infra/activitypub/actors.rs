@@ -0,0 +1,472 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use lru::LruCache;
+use serde_json::Value;
+
+use crate::{
+    federation::{self, RemoteActor, RemoteActorPersistence, RemoteActorUseCase},
+    infra::persistence::RemoteActorPgPersistence,
+};
+
+use super::{
+    client::ActivityPubClient,
+    objects::{self, host_of},
+    signature::Signer,
+};
+
+const CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(1000).unwrap();
+
+/// Looks up remote actors and the keys they sign with, in memory, then in the database,
+/// and only then on their server.
+#[derive(Clone)]
+pub struct RemoteActorStore<P = RemoteActorPgPersistence> {
+    client: ActivityPubClient,
+    persistence: P,
+    cache: Arc<Mutex<ActorCache>>,
+}
+
+struct ActorCache {
+    actors: LruCache<String, RemoteActor>,
+    /// Which cached actor a key id belongs to.
+    key_owners: HashMap<String, String>,
+}
+
+impl<P: RemoteActorPersistence + Clone + Sync> RemoteActorStore<P> {
+    pub fn new(client: ActivityPubClient, persistence: P) -> Self {
+        Self {
+            client,
+            persistence,
+            cache: Arc::new(Mutex::new(ActorCache {
+                actors: LruCache::new(CACHE_CAPACITY),
+                key_owners: HashMap::new(),
+            })),
+        }
+    }
+
+    pub async fn get_actor(&self, actor_id: &str, signer: &Signer) -> Option<RemoteActor> {
+        let cached = self.find_cached(actor_id).await;
+        match cached {
+            Some(actor) if !federation::is_stale(&actor, Utc::now()) => Some(actor),
+            // a server that is down does not make its actors unknown
+            _ => self.fetch_actor(actor_id, signer).await.or(cached),
+        }
+    }
+
+    pub async fn get_key_owner(&self, key_id: &str, signer: &Signer) -> Option<RemoteActor> {
+        let cached = self.find_cached_by_key(key_id).await;
+        match cached {
+            Some(actor) if !federation::is_stale(&actor, Utc::now()) => Some(actor),
+            _ => self.fetch_key_owner(key_id, signer).await.or(cached),
+        }
+    }
+
+    /// Fetches the key again after it failed to verify, the actor may have rotated it.
+    pub async fn refetch_key_owner(&self, key_id: &str, signer: &Signer) -> Option<RemoteActor> {
+        let cached = self.find_cached_by_key(key_id).await;
+        if cached.is_some_and(|actor| !federation::may_refetch(&actor, Utc::now())) {
+            return None;
+        }
+        self.fetch_key_owner(key_id, signer).await
+    }
+
+    async fn find_cached(&self, actor_id: &str) -> Option<RemoteActor> {
+        if let Some(actor) = self.cache.lock().ok()?.actors.get(actor_id) {
+            return Some(actor.clone());
+        }
+        let actor = self.use_case().get_actor(actor_id).await.ok()?;
+        self.remember(&actor);
+        Some(actor)
+    }
+
+    async fn find_cached_by_key(&self, key_id: &str) -> Option<RemoteActor> {
+        {
+            let mut cache = self.cache.lock().ok()?;
+            if let Some(actor_id) = cache.key_owners.get(key_id).cloned()
+                && let Some(actor) = cache.actors.get(&actor_id)
+            {
+                return Some(actor.clone());
+            }
+        }
+        let actor = self.use_case().get_actor_by_key(key_id).await.ok()?;
+        self.remember(&actor);
+        Some(actor)
+    }
+
+    async fn fetch_actor(&self, actor_id: &str, signer: &Signer) -> Option<RemoteActor> {
+        let document = self.client.fetch(actor_id, signer).await.ok()?;
+        // the fetched document has to be the actor that was asked for
+        if objects::id_of(&document) != Some(actor_id) {
+            return None;
+        }
+        let actor = to_remote_actor(&document, None)?;
+        self.save(&actor).await;
+        Some(actor)
+    }
+
+    async fn fetch_key_owner(&self, key_id: &str, signer: &Signer) -> Option<RemoteActor> {
+        // the key is usually a fragment of the actor document
+        let document_url = key_id.split('#').next()?;
+        let document = self.client.fetch(document_url, signer).await.ok()?;
+        // otherwise it is a document of its own that names its owner
+        let document = match document.get("owner").and_then(Value::as_str) {
+            Some(owner) if document.get("inbox").is_none() => {
+                self.client.fetch(owner, signer).await.ok()?
+            }
+            _ => document,
+        };
+        let actor = to_remote_actor(&document, Some(key_id))?;
+        self.save(&actor).await;
+        Some(actor)
+    }
+
+    async fn save(&self, actor: &RemoteActor) {
+        // the actor is still good for this request if it cannot be stored
+        if self.use_case().save_actor(actor).await.is_err() {
+            eprintln!("Could not store the actor {}", actor.actor_id);
+        }
+        self.remember(actor);
+    }
+
+    fn remember(&self, actor: &RemoteActor) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        let previous = cache.actors.push(actor.actor_id.clone(), actor.clone());
+        // an evicted actor, or the old version of this one, may have had another key
+        if let Some((_, previous)) = previous
+            && cache.key_owners.get(&previous.key_id) == Some(&previous.actor_id)
+        {
+            cache.key_owners.remove(&previous.key_id);
+        }
+        cache
+            .key_owners
+            .insert(actor.key_id.clone(), actor.actor_id.clone());
+    }
+
+    fn use_case(&self) -> impl RemoteActorUseCase {
+        federation::remote_actor_use_case(self.persistence.clone())
+    }
+}
+
+/// Reads an actor document, with the key of the given id or else its only key.
+fn to_remote_actor(document: &Value, key_id: Option<&str>) -> Option<RemoteActor> {
+    let actor_id = objects::id_of(document)?;
+    let key = match document.get("publicKey")? {
+        Value::Array(keys) => match key_id {
+            Some(key_id) => keys
+                .iter()
+                .find(|key| key.get("id").and_then(Value::as_str) == Some(key_id))?,
+            None => keys.first()?,
+        },
+        key => key,
+    };
+    let key_id = match (key.get("id").and_then(Value::as_str)?, key_id) {
+        (id, Some(key_id)) if id != key_id => return None,
+        (id, _) => id,
+    };
+    if key
+        .get("owner")
+        .and_then(Value::as_str)
+        .is_some_and(|owner| owner != actor_id)
+    {
+        return None;
+    }
+    // a server may only publish keys for its own actors
+    if host_of(actor_id)? != host_of(key_id)? {
+        return None;
+    }
+    let text = |name: &str| {
+        document
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+    };
+    Some(RemoteActor {
+        actor_id: actor_id.to_owned(),
+        inbox: text("inbox")?,
+        shared_inbox: document
+            .get("endpoints")
+            .and_then(|endpoints| endpoints.get("sharedInbox"))
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        preferred_username: text("preferredUsername"),
+        name: text("name"),
+        key_id: key_id.to_owned(),
+        public_key_pem: key.get("publicKeyPem")?.as_str()?.to_owned(),
+        fetched_at: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        routing::get,
+    };
+    use chrono::TimeDelta;
+    use rsa::{
+        RsaPrivateKey,
+        pkcs8::{EncodePrivateKey, LineEnding},
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::federation::RemoteActorError;
+
+    #[derive(Clone, Default)]
+    struct MemoryPersistence {
+        actors: Arc<Mutex<HashMap<String, RemoteActor>>>,
+    }
+
+    impl RemoteActorPersistence for MemoryPersistence {
+        async fn find_remote_actor(&self, actor_id: &str) -> Result<RemoteActor, RemoteActorError> {
+            let actors = self.actors.lock().unwrap();
+            actors
+                .get(actor_id)
+                .cloned()
+                .ok_or(RemoteActorError::NotFound)
+        }
+
+        async fn find_remote_actor_by_key(
+            &self,
+            key_id: &str,
+        ) -> Result<RemoteActor, RemoteActorError> {
+            let actors = self.actors.lock().unwrap();
+            actors
+                .values()
+                .find(|actor| actor.key_id == key_id)
+                .cloned()
+                .ok_or(RemoteActorError::NotFound)
+        }
+
+        async fn upsert_remote_actor(&self, actor: &RemoteActor) -> Result<(), RemoteActorError> {
+            let mut actors = self.actors.lock().unwrap();
+            actors.insert(actor.actor_id.clone(), actor.clone());
+            Ok(())
+        }
+    }
+
+    /// Counts the documents it serves, bumping `key_version` rotates every actor's key.
+    #[derive(Clone)]
+    struct MockServer {
+        base: String,
+        hits: Arc<AtomicUsize>,
+        key_version: Arc<AtomicUsize>,
+    }
+
+    impl MockServer {
+        async fn start() -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let server = MockServer {
+                base: format!("http://{}", listener.local_addr().unwrap()),
+                hits: Arc::new(AtomicUsize::new(0)),
+                key_version: Arc::new(AtomicUsize::new(0)),
+            };
+            let router = Router::new()
+                .route("/users/{name}", get(actor_document))
+                .with_state(server.clone());
+            tokio::spawn(async move { axum::serve(listener, router).await });
+            server
+        }
+
+        fn actor_id(&self, name: &str) -> String {
+            format!("{}/users/{}", self.base, name)
+        }
+
+        fn hits(&self) -> usize {
+            self.hits.load(Ordering::SeqCst)
+        }
+    }
+
+    async fn actor_document(
+        State(server): State<MockServer>,
+        Path(name): Path<String>,
+    ) -> Json<Value> {
+        server.hits.fetch_add(1, Ordering::SeqCst);
+        let actor_id = server.actor_id(&name);
+        let summary = match name.as_str() {
+            "huge" => "x".repeat(2 * 1024 * 1024),
+            _ => String::new(),
+        };
+        Json(json!({
+            "id": actor_id,
+            "type": "Person",
+            "inbox": format!("{}/inbox", actor_id),
+            "summary": summary,
+            "publicKey": {
+                "id": format!("{}#main-key", actor_id),
+                "owner": actor_id,
+                "publicKeyPem": format!("key-{}", server.key_version.load(Ordering::SeqCst)),
+            },
+        }))
+    }
+
+    fn store() -> RemoteActorStore<MemoryPersistence> {
+        RemoteActorStore::new(ActivityPubClient::new(true), MemoryPersistence::default())
+    }
+
+    fn signer() -> Signer {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        Signer {
+            key_id: "http://localhost/boards/test#main-key".to_owned(),
+            private_key_pem: key.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+        }
+    }
+
+    fn actor(actor_id: &str, key_pem: &str, age: TimeDelta) -> RemoteActor {
+        RemoteActor {
+            actor_id: actor_id.to_owned(),
+            inbox: format!("{}/inbox", actor_id),
+            shared_inbox: None,
+            preferred_username: None,
+            name: None,
+            key_id: format!("{}#main-key", actor_id),
+            public_key_pem: key_pem.to_owned(),
+            fetched_at: Utc::now() - age,
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_cached_actors() {
+        let server = MockServer::start().await;
+        let (store, signer) = (store(), signer());
+        let actor_id = server.actor_id("alice");
+
+        let first = store.get_actor(&actor_id, &signer).await.unwrap();
+        let second = store.get_actor(&actor_id, &signer).await.unwrap();
+        let by_key = store.get_key_owner(&first.key_id, &signer).await.unwrap();
+
+        assert_eq!(server.hits(), 1);
+        assert_eq!(second.public_key_pem, "key-0");
+        assert_eq!(by_key.actor_id, actor_id);
+        assert!(
+            store
+                .persistence
+                .actors
+                .lock()
+                .unwrap()
+                .contains_key(&actor_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_stale_actors_again() {
+        let server = MockServer::start().await;
+        let (store, signer) = (store(), signer());
+        let fresh_id = server.actor_id("fresh");
+        let stale_id = server.actor_id("stale");
+        for stored in [
+            actor(&fresh_id, "stored", TimeDelta::hours(23)),
+            actor(&stale_id, "stored", TimeDelta::hours(25)),
+        ] {
+            store.persistence.upsert_remote_actor(&stored).await.ok();
+        }
+
+        let fresh = store.get_actor(&fresh_id, &signer).await.unwrap();
+        assert_eq!(fresh.public_key_pem, "stored");
+        assert_eq!(server.hits(), 0);
+
+        let stale = store.get_actor(&stale_id, &signer).await.unwrap();
+        assert_eq!(stale.public_key_pem, "key-0");
+        assert_eq!(server.hits(), 1);
+        let stored = store.persistence.find_remote_actor(&stale_id).await.ok();
+        assert_eq!(stored.unwrap().public_key_pem, "key-0");
+    }
+
+    #[tokio::test]
+    async fn keeps_stale_actors_while_their_server_is_down() {
+        let (store, signer) = (store(), signer());
+        let actor_id = "http://127.0.0.1:1/users/gone";
+        let stored = actor(actor_id, "stored", TimeDelta::hours(25));
+        store.persistence.upsert_remote_actor(&stored).await.ok();
+
+        let actor = store.get_actor(actor_id, &signer).await.unwrap();
+
+        assert_eq!(actor.public_key_pem, "stored");
+    }
+
+    #[tokio::test]
+    async fn refetches_rotated_keys_at_most_once_a_minute() {
+        let server = MockServer::start().await;
+        let (store, signer) = (store(), signer());
+        let actor_id = server.actor_id("rotating");
+        let key_id = format!("{}#main-key", actor_id);
+
+        let owner = store.get_key_owner(&key_id, &signer).await.unwrap();
+        assert_eq!(owner.public_key_pem, "key-0");
+        server.key_version.store(1, Ordering::SeqCst);
+
+        // just fetched, a bad signature does not warrant another fetch
+        assert!(store.refetch_key_owner(&key_id, &signer).await.is_none());
+        assert_eq!(server.hits(), 1);
+
+        store.remember(&actor(&actor_id, "key-0", TimeDelta::minutes(2)));
+        let rotated = store.refetch_key_owner(&key_id, &signer).await.unwrap();
+        assert_eq!(rotated.public_key_pem, "key-1");
+        assert_eq!(server.hits(), 2);
+        let cached = store.get_key_owner(&key_id, &signer).await.unwrap();
+        assert_eq!(cached.public_key_pem, "key-1");
+        assert_eq!(server.hits(), 2);
+    }
+
+    #[test]
+    fn forgets_the_keys_of_evicted_actors() {
+        let store = store();
+        let actor_ids: Vec<String> = (0..=CACHE_CAPACITY.get())
+            .map(|n| format!("https://remote.example/users/{}", n))
+            .collect();
+        for actor_id in &actor_ids {
+            store.remember(&actor(actor_id, "key", TimeDelta::zero()));
+        }
+
+        let cache = store.cache.lock().unwrap();
+        assert_eq!(cache.key_owners.len(), CACHE_CAPACITY.get());
+        assert!(
+            !cache
+                .key_owners
+                .contains_key(&format!("{}#main-key", actor_ids[0]))
+        );
+        assert!(
+            cache
+                .key_owners
+                .contains_key(&format!("{}#main-key", actor_ids[1]))
+        );
+    }
+
+    #[test]
+    fn forgets_replaced_keys() {
+        let store = store();
+        let actor_id = "https://remote.example/users/alice";
+        let mut rotated = actor(actor_id, "new", TimeDelta::zero());
+        rotated.key_id = format!("{}#new-key", actor_id);
+
+        store.remember(&actor(actor_id, "old", TimeDelta::zero()));
+        store.remember(&rotated);
+
+        let cache = store.cache.lock().unwrap();
+        assert_eq!(cache.key_owners.len(), 1);
+        assert_eq!(
+            cache.key_owners.get(&rotated.key_id).map(String::as_str),
+            Some(actor_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_oversized_documents() {
+        let server = MockServer::start().await;
+        let (store, signer) = (store(), signer());
+        let actor_id = server.actor_id("huge");
+
+        assert!(store.get_actor(&actor_id, &signer).await.is_none());
+        assert_eq!(server.hits(), 1);
+        assert!(store.persistence.actors.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::http::{Method, header};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use serde_json::Value;
+use url::{Host, Url};
+
+use super::signature::{Signer, sign_request};
+
+/// Actors and activities are small, anything bigger is not read.
+const MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+
+pub enum ClientError {
+    InvalidUrl,
+    RequestFailed,
+    TooLarge,
+    Malformed,
+}
+
+/// Urls come from remote input, so unless `allow_private` is set only public addresses are
+/// reached, after every redirect and whatever a host name resolves to.
+#[derive(Clone)]
+pub struct ActivityPubClient {
+    http: reqwest::Client,
+    allow_private: bool,
+}
+
+impl ActivityPubClient {
+    pub fn new(allow_private: bool) -> Self {
+        let redirect_policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if check_remote_url(attempt.url(), allow_private).is_err() {
+                attempt.error("redirect to a non-public address")
+            } else {
+                attempt.follow()
+            }
+        });
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(redirect_policy);
+        if !allow_private {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        let http = builder.build().expect("Could not build the http client");
+        Self {
+            http,
+            allow_private,
+        }
+    }
+
+    pub async fn fetch(&self, url: &str, signer: &Signer) -> Result<Value, ClientError> {
+        let url = self.parse_remote_url(url)?;
+        let signed = sign_request(&Method::GET, &url, None, signer)
+            .map_err(|_| ClientError::RequestFailed)?;
+        let mut response = self
+            .http
+            .get(url)
+            .headers(signed)
+            .header(header::ACCEPT, "application/activity+json")
+            .send()
+            .await
+            .map_err(|_| ClientError::RequestFailed)?;
+        if !response.status().is_success() {
+            return Err(ClientError::RequestFailed);
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_RESPONSE_SIZE as u64)
+        {
+            return Err(ClientError::TooLarge);
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|_| ClientError::RequestFailed)?
+        {
+            if body.len() + chunk.len() > MAX_RESPONSE_SIZE {
+                return Err(ClientError::TooLarge);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        serde_json::from_slice(&body).map_err(|_| ClientError::Malformed)
+    }
+
+    pub async fn deliver(
+        &self,
+        inbox: &str,
+        activity: &Value,
+        signer: &Signer,
+    ) -> Result<(), ClientError> {
+        let url = self.parse_remote_url(inbox)?;
+        let body = serde_json::to_vec(activity).map_err(|_| ClientError::Malformed)?;
+        let signed = sign_request(&Method::POST, &url, Some(&body), signer)
+            .map_err(|_| ClientError::RequestFailed)?;
+        let response = self
+            .http
+            .post(url)
+            .headers(signed)
+            .header(header::CONTENT_TYPE, "application/activity+json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|_| ClientError::RequestFailed)?;
+        if !response.status().is_success() {
+            return Err(ClientError::RequestFailed);
+        }
+        Ok(())
+    }
+
+    fn parse_remote_url(&self, url: &str) -> Result<Url, ClientError> {
+        let url = Url::parse(url).map_err(|_| ClientError::InvalidUrl)?;
+        check_remote_url(&url, self.allow_private)?;
+        Ok(url)
+    }
+}
+
+/// Host names are checked once resolved, literal addresses never reach the resolver.
+fn check_remote_url(url: &Url, allow_private: bool) -> Result<(), ClientError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ClientError::InvalidUrl);
+    }
+    let address = match url.host() {
+        None => return Err(ClientError::InvalidUrl),
+        Some(Host::Domain(_)) => return Ok(()),
+        Some(Host::Ipv4(address)) => IpAddr::V4(address),
+        Some(Host::Ipv6(address)) => IpAddr::V6(address),
+    };
+    match allow_private || is_public(&address) {
+        true => Ok(()),
+        false => Err(ClientError::InvalidUrl),
+    }
+}
+
+/// Resolves like the system does, but drops every address that is not public.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_public(&address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_public_v4(address),
+        IpAddr::V6(address) => is_public_v6(address),
+    }
+}
+
+fn is_public_v4(address: &Ipv4Addr) -> bool {
+    let [first, second, third, _] = address.octets();
+    let is_this_network = first == 0;
+    let is_shared = first == 100 && second & 0xc0 == 64;
+    let is_protocol_assignment = first == 192 && second == 0 && third == 0;
+    let is_benchmarking = first == 198 && second & 0xfe == 18;
+    // 240.0.0.0/4 includes the broadcast address
+    let is_reserved = first >= 240;
+    !(address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_unspecified()
+        || address.is_multicast()
+        || address.is_documentation()
+        || is_this_network
+        || is_shared
+        || is_protocol_assignment
+        || is_benchmarking
+        || is_reserved)
+}
+
+fn is_public_v6(address: &Ipv6Addr) -> bool {
+    if address.is_loopback() || address.is_unspecified() {
+        return false;
+    }
+    // an embedded v4 address is reached through the v4 network, so it has to be public there
+    if let Some(embedded) = embedded_v4(address) {
+        return is_public_v4(&embedded);
+    }
+    let segments = address.segments();
+    let is_local_nat64 = segments[0] == 0x64 && segments[1] == 0xff9b && segments[2] == 1;
+    let is_documentation = segments[0] == 0x2001 && segments[1] == 0xdb8;
+    !(address.is_unique_local()
+        || address.is_unicast_link_local()
+        || address.is_multicast()
+        || is_local_nat64
+        || is_documentation)
+}
+
+/// The v4 address of an ipv4-mapped, ipv4-compatible, NAT64 or 6to4 address.
+fn embedded_v4(address: &Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(mapped) = address.to_ipv4_mapped() {
+        return Some(mapped);
+    }
+    let segments = address.segments();
+    let octets = address.octets();
+    let last_v4 = || Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]);
+    match segments {
+        [0, 0, 0, 0, 0, 0, _, _] => Some(last_v4()),
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(last_v4()),
+        [0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_public_str(address: &str) -> bool {
+        is_public(&address.parse().unwrap())
+    }
+
+    #[test]
+    fn rejects_internal_addresses() {
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a00:1",
+            "64:ff9b:1::1",
+            "2002:7f00:1::1",
+            "2002:a9fe:a9fe::",
+            "ff02::1",
+            "2001:db8::1",
+            "224.0.0.1",
+            "239.255.255.250",
+            "240.0.0.1",
+            "255.255.255.255",
+            "198.18.0.1",
+            "198.19.255.255",
+            "192.0.2.1",
+            "198.51.100.1",
+            "203.0.113.1",
+            "192.0.0.8",
+            "0.1.2.3",
+        ] {
+            assert!(!is_public_str(address), "{} should not be public", address);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for address in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "198.20.0.1",
+            "2606:4700::1111",
+            "::ffff:93.184.216.34",
+            "64:ff9b::5db8:d822",
+            "2002:5db8:d822::1",
+        ] {
+            assert!(is_public_str(address), "{} should be public", address);
+        }
+    }
+
+    #[test]
+    fn checks_literal_hosts() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(check_remote_url(&url("http://169.254.169.254/latest"), false).is_err());
+        assert!(check_remote_url(&url("http://[::1]:8080/actor"), false).is_err());
+        assert!(check_remote_url(&url("ftp://example.com/actor"), false).is_err());
+        assert!(check_remote_url(&url("https://example.com/actor"), false).is_ok());
+        assert!(check_remote_url(&url("http://127.0.0.1:9100/actor"), true).is_ok());
+    }
+}
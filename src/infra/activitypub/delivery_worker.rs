@@ -0,0 +1,106 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use crate::{
+    board::BoardError,
+    federation::{Delivery, DeliveryUseCase},
+    infra::AppState,
+};
+
+use super::{board_signer, signature::Signer};
+
+const BATCH_SIZE: i64 = 20;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn spawn_delivery_worker(app_state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let claimed = app_state
+                .di
+                .delivery_use_case()
+                .claim_due_deliveries(BATCH_SIZE)
+                .await;
+            match claimed {
+                Ok(deliveries) => {
+                    let is_full = deliveries.len() as i64 == BATCH_SIZE;
+                    deliver_batch(&app_state, deliveries).await;
+                    if is_full {
+                        continue; // more may be due already
+                    }
+                }
+                Err(_) => eprintln!("Could not load due deliveries"),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+async fn deliver_batch(app_state: &AppState, deliveries: Vec<Delivery>) {
+    let mut signers: HashMap<String, Result<Signer, BoardError>> = HashMap::new();
+    let mut sends = JoinSet::new();
+    for delivery in deliveries {
+        if !signers.contains_key(&delivery.board_name) {
+            let signer = board_signer(app_state, &delivery.board_name).await;
+            signers.insert(delivery.board_name.clone(), signer);
+        }
+        let signer = match &signers[&delivery.board_name] {
+            Ok(signer) => signer.clone(),
+            Err(err) => {
+                set_aside(app_state, &delivery, err).await;
+                continue;
+            }
+        };
+        let app_state = app_state.clone();
+        sends.spawn(async move { deliver(&app_state, delivery, signer).await });
+    }
+    sends.join_all().await;
+}
+
+/// Faults on our side say nothing about the inbox, they never count as a failed attempt.
+async fn set_aside(app_state: &AppState, delivery: &Delivery, err: &BoardError) {
+    let delivery_use_case = app_state.di.delivery_use_case();
+    let recorded = match err {
+        BoardError::NotFound => delivery_use_case.discard(delivery).await,
+        _ => delivery_use_case.postpone(delivery).await,
+    };
+    if recorded.is_err() {
+        eprintln!("Could not record the delivery to {}", delivery.inbox);
+    }
+}
+
+async fn deliver(app_state: &AppState, delivery: Delivery, signer: Signer) {
+    let delivery_use_case = app_state.di.delivery_use_case();
+    let recorded = match serde_json::from_str::<Value>(&delivery.activity) {
+        Ok(activity) if send(app_state, &delivery, &activity, signer).await => {
+            delivery_use_case.delivered(&delivery).await
+        }
+        Ok(_) => delivery_use_case.failed(&delivery).await,
+        Err(_) => delivery_use_case.discard(&delivery).await,
+    };
+    if recorded.is_err() {
+        eprintln!("Could not record the delivery to {}", delivery.inbox);
+    }
+}
+
+async fn send(
+    app_state: &AppState,
+    delivery: &Delivery,
+    activity: &Value,
+    mut signer: Signer,
+) -> bool {
+    // replies to mirrored groups are sent by the board's poster
+    let poster_id = app_state.federation.board_poster_id(&delivery.board_name);
+    if activity.get("actor").and_then(Value::as_str) == Some(poster_id.as_str()) {
+        signer.key_id = app_state
+            .federation
+            .board_poster_key_id(&delivery.board_name);
+    }
+    app_state
+        .activitypub
+        .deliver(&delivery.inbox, activity, &signer)
+        .await
+        .is_ok()
+}
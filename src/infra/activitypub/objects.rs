@@ -0,0 +1,491 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    board::{Board, BoardKeys},
+    thread::{Attachment, Post, RemoteOrigin, Thread},
+};
+
+use super::FederationConfig;
+
+pub const ACTIVITY_STREAMS: &str = "https://www.w3.org/ns/activitystreams";
+pub const SECURITY_V1: &str = "https://w3id.org/security/v1";
+pub const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Remote links clients may follow, anything else could run script in the page.
+const LINK_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// A board as seen by other servers, Lemmy calls these communities.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Group {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub preferred_username: String,
+    pub name: String,
+    pub inbox: String,
+    pub outbox: String,
+    pub followers: String,
+    pub public_key: PublicKey,
+}
+
+/// Writes the local replies of a mirrored board, see [FederationConfig::board_poster_id].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub preferred_username: String,
+    pub name: String,
+    pub inbox: String,
+    pub public_key: PublicKey,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKey {
+    pub id: String,
+    pub owner: String,
+    pub public_key_pem: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Activity {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub to: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cc: Vec<String>,
+    pub object: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderedCollection {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub total_items: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderedCollectionPage {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub part_of: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    pub ordered_items: Vec<Activity>,
+}
+
+/// A post, the first one of a thread is a `Page` like on Lemmy, replies are `Note`s.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostObject {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Posting is anonymous, the board speaks for all of its posts, or its poster on mirrors.
+    pub attributed_to: String,
+    pub audience: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub content: String,
+    pub media_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<DateTime<Utc>>,
+    pub sensitive: bool,
+    pub attachment: Vec<Document>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WithContext<T> {
+    #[serde(rename = "@context")]
+    pub context: String,
+    #[serde(flatten)]
+    pub object: T,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub media_type: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+pub fn id_of(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(object) => object.get("id")?.as_str(),
+        value => value.as_str(),
+    }
+}
+
+/// Servers vouch for the ids on their own host only.
+pub fn host_of(id: &str) -> Option<String> {
+    Url::parse(id).ok()?.host_str().map(str::to_owned)
+}
+
+pub fn kind_of(value: &Value) -> Option<&str> {
+    value.get("type")?.as_str()
+}
+
+pub fn board_activity(
+    config: &FederationConfig,
+    board_name: &str,
+    kind: &str,
+    object: Value,
+) -> Activity {
+    let actor_id = config.board_actor_id(board_name);
+    Activity {
+        context: ACTIVITY_STREAMS.to_owned(),
+        id: format!("{}/activities/{}", actor_id, Uuid::new_v4()),
+        kind: kind.to_owned(),
+        actor: actor_id,
+        to: Vec::new(),
+        cc: Vec::new(),
+        object,
+    }
+}
+
+/// The board's `Create` of a post, its id follows from the post's so a backfill matches the delivery.
+pub fn create_activity(
+    config: &FederationConfig,
+    board_name: &str,
+    object: PostObject,
+) -> Activity {
+    let (to, cc) = (object.to.clone(), object.cc.clone());
+    let id = format!("{}#create", object.id);
+    let actor = object.attributed_to.clone();
+    let object = serde_json::to_value(object).unwrap_or_default();
+    Activity {
+        id,
+        actor,
+        to,
+        cc,
+        ..board_activity(config, board_name, "Create", object)
+    }
+}
+
+/// The board's `Announce` of a federated reply, as Lemmy groups pass on what their members post.
+/// Like Lemmy, the board announces the author's `Create`, replies that arrived before it was kept
+/// are announced by the note's id.
+pub fn announce_activity(
+    config: &FederationConfig,
+    board_name: &str,
+    thread_id: &Uuid,
+    post_id: &Uuid,
+    remote: &RemoteOrigin,
+) -> Activity {
+    let object = remote
+        .activity
+        .as_deref()
+        .and_then(|activity| serde_json::from_str(activity).ok())
+        .unwrap_or_else(|| Value::String(remote.object_id.clone()));
+    let id = format!(
+        "{}#announce",
+        config.post_object_id(board_name, thread_id, post_id)
+    );
+    Activity {
+        id,
+        to: vec![PUBLIC.to_owned()],
+        cc: vec![config.board_followers(board_name)],
+        ..board_activity(config, board_name, "Announce", object)
+    }
+}
+
+pub fn to_group(config: &FederationConfig, board: &Board, keys: &BoardKeys) -> Group {
+    let actor_id = config.board_actor_id(&board.name);
+    Group {
+        context: vec![ACTIVITY_STREAMS.to_owned(), SECURITY_V1.to_owned()],
+        id: actor_id.clone(),
+        kind: "Group".to_owned(),
+        preferred_username: board.name.clone(),
+        name: format!("/{}/", board.name),
+        inbox: config.board_inbox(&board.name),
+        outbox: config.board_outbox(&board.name),
+        followers: config.board_followers(&board.name),
+        public_key: PublicKey {
+            id: config.board_key_id(&board.name),
+            owner: actor_id,
+            public_key_pem: keys.public_key_pem.clone(),
+        },
+    }
+}
+
+pub fn to_poster(config: &FederationConfig, board: &Board, keys: &BoardKeys) -> Person {
+    let poster_id = config.board_poster_id(&board.name);
+    Person {
+        context: vec![ACTIVITY_STREAMS.to_owned(), SECURITY_V1.to_owned()],
+        id: poster_id.clone(),
+        kind: "Person".to_owned(),
+        preferred_username: format!("{}_anon", board.name),
+        name: format!("Anonymous on /{}/", board.name),
+        inbox: config.board_poster_inbox(&board.name),
+        public_key: PublicKey {
+            id: config.board_poster_key_id(&board.name),
+            owner: poster_id,
+            public_key_pem: keys.public_key_pem.clone(),
+        },
+    }
+}
+
+pub fn to_post_object(
+    config: &FederationConfig,
+    board: &Board,
+    thread: &Thread,
+    post: &Post,
+) -> PostObject {
+    let thread_object_id = config.thread_object_id(&board.name, &thread.thread_id);
+    let opening_post = thread.posts.posts.first();
+    let is_opening_post = opening_post.is_some_and(|first| first.id == post.id);
+    let (id, kind, name, in_reply_to) = if is_opening_post {
+        // Lemmy refuses pages without a title
+        let name = post
+            .subject
+            .clone()
+            .unwrap_or(format!("/{}/ thread", board.name));
+        (thread_object_id, "Page", Some(name), None)
+    } else {
+        let id = config.post_object_id(&board.name, &thread.thread_id, &post.id);
+        // a mirrored thread is the group's post
+        let in_reply_to = opening_post
+            .and_then(|first| first.remote.as_ref())
+            .map_or(thread_object_id, |remote| remote.object_id.clone());
+        (id, "Note", post.subject.clone(), Some(in_reply_to))
+    };
+    let actor_id = config.board_actor_id(&board.name);
+    let (attributed_to, audience, cc) = match &board.mirror {
+        Some(mirror) => (
+            config.board_poster_id(&board.name),
+            mirror.actor_id.clone(),
+            vec![mirror.actor_id.clone()],
+        ),
+        None => (
+            actor_id.clone(),
+            actor_id,
+            vec![config.board_followers(&board.name)],
+        ),
+    };
+    PostObject {
+        id,
+        kind: kind.to_owned(),
+        attributed_to,
+        audience,
+        to: vec![PUBLIC.to_owned()],
+        cc,
+        name,
+        content: to_html(post.content.as_deref().unwrap_or_default()),
+        media_type: "text/html".to_owned(),
+        in_reply_to,
+        published: post.published,
+        sensitive: post.spoiler,
+        attachment: post
+            .attachments
+            .iter()
+            .map(|attachment| to_document(config, attachment))
+            .collect(),
+    }
+}
+
+/// Takes a remote `Note` as a reply, the note's `summary` stands in for a missing subject.
+pub fn to_remote_post(actor_id: &str, note: &Value) -> Option<Post> {
+    let text = |key: &str| note.get(key).and_then(Value::as_str).map(str::to_owned);
+    let attachments = match note.get("attachment") {
+        Some(Value::Array(documents)) => documents.iter().filter_map(to_attachment).collect(),
+        Some(document) => to_attachment(document).into_iter().collect(),
+        None => Vec::new(),
+    };
+    Some(Post {
+        id: Uuid::new_v4(),
+        name: None,
+        subject: text("name").or(text("summary")),
+        content: text("content").map(|content| sanitize_html(&content)),
+        media_url: None,
+        attachments,
+        spoiler: note
+            .get("sensitive")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        remote: Some(RemoteOrigin {
+            actor_id: actor_id.to_owned(),
+            object_id: id_of(note)?.to_owned(),
+            activity: None,
+        }),
+        published: Some(Utc::now()), // when it reached the board
+        edited: None,
+        deleted: None,
+        reactions: Vec::new(),
+    })
+}
+
+/// Remote files are linked, not copied, so their size is unknown.
+fn to_attachment(document: &Value) -> Option<Attachment> {
+    let url = match document.get("url")? {
+        Value::Object(link) => link.get("href")?.as_str()?,
+        url => url.as_str()?,
+    };
+    if !Url::parse(url).is_ok_and(|url| LINK_SCHEMES.contains(&url.scheme())) {
+        return None;
+    }
+    let dimension = |key: &str| {
+        document
+            .get(key)
+            .and_then(Value::as_u64)
+            .and_then(|value| u32::try_from(value).ok())
+    };
+    Some(Attachment {
+        hash: None,
+        url: url.to_owned(),
+        mime_type: document
+            .get("mediaType")
+            .and_then(Value::as_str)
+            .unwrap_or("application/octet-stream")
+            .to_owned(),
+        size: 0,
+        width: dimension("width"),
+        height: dimension("height"),
+        thumbnail_url: None,
+        duration: None,
+        codec: None,
+    })
+}
+
+/// Keeps the formatting and links of remote posts, drops everything that could run or embed.
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::Builder::empty()
+        .add_tags([
+            "p",
+            "br",
+            "a",
+            "span",
+            "strong",
+            "em",
+            "b",
+            "i",
+            "u",
+            "s",
+            "del",
+            "code",
+            "pre",
+            "blockquote",
+            "ul",
+            "ol",
+            "li",
+        ])
+        .add_tag_attributes("a", ["href"])
+        .url_schemes(HashSet::from(LINK_SCHEMES))
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .clean(html)
+        .to_string()
+}
+
+fn to_document(config: &FederationConfig, attachment: &Attachment) -> Document {
+    Document {
+        kind: "Document".to_owned(),
+        media_type: attachment.mime_type.clone(),
+        url: config.absolute_url(&attachment.url),
+        width: attachment.width,
+        height: attachment.height,
+    }
+}
+
+/// Posts are plain text, other servers expect html.
+fn to_html(text: &str) -> String {
+    let escaped = text
+        .replace("\r\n", "\n")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+    let paragraphs: Vec<String> = escaped
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .map(|paragraph| format!("<p>{}</p>", paragraph.trim().replace('\n', "<br>")))
+        .collect();
+    paragraphs.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn strips_scripts_styles_and_unsafe_links() {
+        let html = sanitize_html(concat!(
+            r#"<p>hi<script>alert(1)</script><style>p { color: red }</style></p>"#,
+            r#"<a href="javascript:alert(1)">a</a><a href="data:text/html,x">b</a>"#,
+            r#"<a href="https://example.org/" onclick="alert(1)">c</a><img src="https://example.org/x.png">"#,
+        ));
+        assert_eq!(
+            html,
+            concat!(
+                "<p>hi</p><a rel=\"nofollow noopener noreferrer\">a</a>",
+                "<a rel=\"nofollow noopener noreferrer\">b</a>",
+                "<a href=\"https://example.org/\" rel=\"nofollow noopener noreferrer\">c</a>",
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_only_http_attachments() {
+        let note = json!({
+            "id": "https://remote.example/notes/1",
+            "content": "<p>x</p><script>alert(1)</script>",
+            "attachment": [
+                { "type": "Document", "url": "javascript:alert(1)" },
+                { "type": "Document", "url": "data:image/png;base64,AAAA" },
+                { "type": "Document", "url": { "href": "JavaScript:alert(1)" } },
+                { "type": "Document", "url": "https://remote.example/a.png", "mediaType": "image/png" },
+                { "type": "Document", "url": { "href": "http://remote.example/b.webm" } },
+            ],
+        });
+        let post = to_remote_post("https://remote.example/users/a", &note).unwrap();
+        assert_eq!(post.content.as_deref(), Some("<p>x</p>"));
+        let urls: Vec<_> = post.attachments.iter().map(|a| a.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://remote.example/a.png",
+                "http://remote.example/b.webm"
+            ]
+        );
+    }
+}
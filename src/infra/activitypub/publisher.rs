@@ -0,0 +1,136 @@
+use crate::{
+    board::{Board, Mirror},
+    federation::{DeliveryUseCase, FederationUseCase, Publisher, RemoteActorUseCase},
+    thread::{Post, Thread},
+};
+
+use super::{
+    FederationConfig,
+    objects::{self, Activity},
+};
+
+/// Wraps new posts into `Create` activities, and federated replies into `Announce`s,
+/// and queues them for every follower of the board.
+pub struct ActivityPublisher<F, D, R>
+where
+    F: FederationUseCase,
+    D: DeliveryUseCase,
+    R: RemoteActorUseCase,
+{
+    config: FederationConfig,
+    federation_use_case: F,
+    delivery_use_case: D,
+    remote_actor_use_case: R,
+}
+
+impl<F: FederationUseCase + Sync, D: DeliveryUseCase + Sync, R: RemoteActorUseCase + Sync>
+    ActivityPublisher<F, D, R>
+{
+    pub fn new(
+        config: FederationConfig,
+        federation_use_case: F,
+        delivery_use_case: D,
+        remote_actor_use_case: R,
+    ) -> Self {
+        Self {
+            config,
+            federation_use_case,
+            delivery_use_case,
+            remote_actor_use_case,
+        }
+    }
+
+    async fn publish_to_followers(&self, board: &Board, activity: Activity) {
+        let followers = self
+            .federation_use_case
+            .get_followers(board)
+            .await
+            .unwrap_or_default();
+        // followers on the same server share one delivery
+        let inboxes = followers
+            .into_iter()
+            .map(|follower| follower.shared_inbox.unwrap_or(follower.inbox))
+            .collect();
+        self.enqueue(board, inboxes, activity).await;
+    }
+
+    /// Local replies in a mirrored thread go to the group, which passes them on to its followers.
+    async fn publish_to_mirror(
+        &self,
+        board: &Board,
+        mirror: &Mirror,
+        thread: &Thread,
+        post: &Post,
+    ) {
+        // the opening post is the group's post that the reply answers
+        if thread
+            .posts
+            .posts
+            .first()
+            .is_none_or(|opening_post| opening_post.remote.is_none())
+        {
+            return;
+        }
+        // the group was fetched when the mirror was set up
+        let group = self.remote_actor_use_case.get_actor(&mirror.actor_id).await;
+        let Ok(group) = group else {
+            eprintln!("Could not find the inbox of {}", mirror.actor_id);
+            return;
+        };
+        // written by the board's poster, in reply to the group's post
+        let object = objects::to_post_object(&self.config, board, thread, post);
+        let create = objects::create_activity(&self.config, &board.name, object);
+        self.enqueue(board, vec![group.inbox], create).await;
+    }
+
+    async fn enqueue(&self, board: &Board, inboxes: Vec<String>, activity: Activity) {
+        let serialized = serde_json::to_string(&activity).unwrap_or_default();
+        if self
+            .delivery_use_case
+            .enqueue(board, inboxes, &serialized)
+            .await
+            .is_err()
+        {
+            eprintln!("Could not queue {} for /{}/", activity.kind, board.name);
+        }
+    }
+}
+
+impl<F: FederationUseCase + Sync, D: DeliveryUseCase + Sync, R: RemoteActorUseCase + Sync> Publisher
+    for ActivityPublisher<F, D, R>
+{
+    async fn publish_thread(&self, board: &Board, thread: &Thread) {
+        let Some(opening_post) = thread.posts.posts.first() else {
+            return;
+        };
+        if opening_post.remote.is_some() {
+            return; // taken from a mirrored group, which published it itself
+        }
+        let object = objects::to_post_object(&self.config, board, thread, opening_post);
+        let create = objects::create_activity(&self.config, &board.name, object);
+        self.publish_to_followers(board, create).await;
+    }
+
+    async fn publish_post(&self, board: &Board, thread: &Thread, post: &Post) {
+        let activity = match (&post.remote, &board.mirror) {
+            // the mirrored group tells its own followers
+            (Some(_), Some(_)) => return,
+            // the author's server only told the board, its followers hear it from the board
+            (Some(remote), None) => objects::announce_activity(
+                &self.config,
+                &board.name,
+                &thread.thread_id,
+                &post.id,
+                remote,
+            ),
+            (None, Some(mirror)) => {
+                return self.publish_to_mirror(board, mirror, thread, post).await;
+            }
+            (None, None) => {
+                let object = objects::to_post_object(&self.config, board, thread, post);
+                objects::create_activity(&self.config, &board.name, object)
+            }
+        };
+        self.publish_to_followers(board, activity).await;
+    }
+}
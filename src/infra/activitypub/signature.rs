@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, header};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, TimeDelta, Utc};
+use rsa::{
+    RsaPrivateKey, RsaPublicKey,
+    pkcs1::DecodeRsaPublicKey,
+    pkcs1v15,
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    signature::{SignatureEncoding, Signer as _, Verifier as _},
+};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// How far the `Date` of a signed request may be off from our clock, in either direction.
+const MAX_DATE_SKEW_HOURS: i64 = 1;
+
+pub enum SignatureError {
+    Missing,
+    Malformed,
+    /// The signature does not cover the headers needed to trust the request.
+    Incomplete,
+    Stale,
+    DigestMismatch,
+    Invalid,
+    KeyError,
+}
+
+/// The parameters of a draft-cavage `Signature` header.
+pub struct SignatureParams {
+    pub key_id: String,
+    pub algorithm: Option<String>,
+    pub headers: Vec<String>,
+    pub signature: Vec<u8>,
+    pub created: Option<i64>,
+    pub expires: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct Signer {
+    pub key_id: String,
+    pub private_key_pem: String,
+}
+
+/// Reads the `Signature` header, or `Authorization: Signature ...` as some servers send it.
+pub fn signature_params(headers: &HeaderMap) -> Result<SignatureParams, SignatureError> {
+    let value = match headers.get("signature") {
+        Some(value) => value.to_str().map_err(|_| SignatureError::Malformed)?,
+        None => headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Signature "))
+            .ok_or(SignatureError::Missing)?,
+    };
+    parse_signature(value).ok_or(SignatureError::Malformed)
+}
+
+fn parse_signature(value: &str) -> Option<SignatureParams> {
+    let mut params = HashMap::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('=')?;
+        let (param, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => after.split_at(after.find(',').unwrap_or(after.len())),
+        };
+        params.insert(name.trim().to_ascii_lowercase(), param);
+        rest = after.trim_start_matches([',', ' ']);
+    }
+    Some(SignatureParams {
+        key_id: params.get("keyid")?.to_string(),
+        algorithm: params.get("algorithm").map(|alg| alg.to_ascii_lowercase()),
+        // the draft defaults to the date header alone
+        headers: params
+            .get("headers")
+            .unwrap_or(&"date")
+            .split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .collect(),
+        signature: STANDARD.decode(params.get("signature")?).ok()?,
+        created: params
+            .get("created")
+            .and_then(|created| created.parse().ok()),
+        expires: params
+            .get("expires")
+            .and_then(|expires| expires.parse().ok()),
+    })
+}
+
+/// Refuses signatures that could be replayed or that leave the target or body unsigned.
+pub fn check_coverage(
+    headers: &HeaderMap,
+    params: &SignatureParams,
+    has_body: bool,
+    now: DateTime<Utc>,
+) -> Result<(), SignatureError> {
+    let covers = |name: &str| params.headers.iter().any(|covered| covered == name);
+    if !covers("(request-target)") || (has_body && !covers("digest")) {
+        return Err(SignatureError::Incomplete);
+    }
+    let signed_at = if covers("date") {
+        let date = headers
+            .get(header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(SignatureError::Incomplete)?;
+        DateTime::parse_from_rfc2822(date)
+            .map_err(|_| SignatureError::Malformed)?
+            .with_timezone(&Utc)
+    } else if covers("(created)") {
+        params
+            .created
+            .and_then(|created| DateTime::from_timestamp(created, 0))
+            .ok_or(SignatureError::Malformed)?
+    } else {
+        return Err(SignatureError::Incomplete);
+    };
+    if (now - signed_at).abs() > TimeDelta::hours(MAX_DATE_SKEW_HOURS) {
+        return Err(SignatureError::Stale);
+    }
+    if params
+        .expires
+        .is_some_and(|expires| expires < now.timestamp())
+    {
+        return Err(SignatureError::Stale);
+    }
+    Ok(())
+}
+
+pub fn signing_string(
+    method: &Method,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    params: &SignatureParams,
+) -> Option<String> {
+    let lines = params
+        .headers
+        .iter()
+        .map(|name| {
+            let value = match name.as_str() {
+                "(request-target)" => {
+                    format!(
+                        "{} {}",
+                        method.as_str().to_ascii_lowercase(),
+                        path_and_query
+                    )
+                }
+                "(created)" => params.created?.to_string(),
+                "(expires)" => params.expires?.to_string(),
+                _ => {
+                    let values = headers
+                        .get_all(name.as_str())
+                        .iter()
+                        .map(|value| value.to_str().ok())
+                        .collect::<Option<Vec<_>>>()?;
+                    if values.is_empty() {
+                        return None;
+                    }
+                    values.join(", ")
+                }
+            };
+            Some(format!("{}: {}", name, value))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(lines.join("\n"))
+}
+
+/// Checks the signature with RSA-SHA256, or with Ed25519 where `hs2019` leaves it to the key.
+pub fn verify_signature(
+    params: &SignatureParams,
+    signing_string: &str,
+    public_key_pem: &str,
+) -> bool {
+    let is_hs2019 = match params.algorithm.as_deref() {
+        None | Some("hs2019") => true,
+        Some("rsa-sha256") => false,
+        Some(_) => return false,
+    };
+    if let Some(public_key) = parse_rsa_public_key(public_key_pem) {
+        let verifying_key = pkcs1v15::VerifyingKey::<Sha256>::new(public_key);
+        return pkcs1v15::Signature::try_from(params.signature.as_slice()).is_ok_and(|signature| {
+            verifying_key
+                .verify(signing_string.as_bytes(), &signature)
+                .is_ok()
+        });
+    }
+    if !is_hs2019 {
+        return false;
+    }
+    let Ok(verifying_key) = ed25519_dalek::VerifyingKey::from_public_key_pem(public_key_pem) else {
+        return false;
+    };
+    ed25519_dalek::Signature::from_slice(&params.signature).is_ok_and(|signature| {
+        verifying_key
+            .verify(signing_string.as_bytes(), &signature)
+            .is_ok()
+    })
+}
+
+fn parse_rsa_public_key(public_key_pem: &str) -> Option<RsaPublicKey> {
+    RsaPublicKey::from_public_key_pem(public_key_pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(public_key_pem))
+        .ok()
+}
+
+pub fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// Accepts the header if its SHA-256 entry matches, other algorithms are not trusted.
+pub fn verify_digest(headers: &HeaderMap, body: &[u8]) -> Result<(), SignatureError> {
+    let value = headers
+        .get("digest")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(SignatureError::DigestMismatch)?;
+    let expected = STANDARD.encode(Sha256::digest(body));
+    let matches = value.split(',').any(|entry| {
+        entry
+            .trim()
+            .split_once('=')
+            .is_some_and(|(alg, hash)| alg.eq_ignore_ascii_case("sha-256") && hash == expected)
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(SignatureError::DigestMismatch)
+    }
+}
+
+pub fn sign_request(
+    method: &Method,
+    url: &Url,
+    body: Option<&[u8]>,
+    signer: &Signer,
+) -> Result<HeaderMap, SignatureError> {
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => return Err(SignatureError::Malformed),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(header::HOST, to_header_value(&host)?);
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    headers.insert(header::DATE, to_header_value(&date)?);
+    let mut covered = vec!["(request-target)", "host", "date"];
+    if let Some(body) = body {
+        headers.insert(
+            HeaderName::from_static("digest"),
+            to_header_value(&digest(body))?,
+        );
+        covered.push("digest");
+    }
+    let path_and_query = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+    let params = SignatureParams {
+        key_id: signer.key_id.clone(),
+        algorithm: Some("rsa-sha256".to_owned()),
+        headers: covered.iter().map(|name| name.to_string()).collect(),
+        signature: Vec::new(),
+        created: None,
+        expires: None,
+    };
+    let signing_string = signing_string(method, &path_and_query, &headers, &params)
+        .ok_or(SignatureError::Malformed)?;
+    let private_key = RsaPrivateKey::from_pkcs8_pem(&signer.private_key_pem)
+        .map_err(|_| SignatureError::KeyError)?;
+    let signature = pkcs1v15::SigningKey::<Sha256>::new(private_key)
+        .sign(signing_string.as_bytes())
+        .to_bytes();
+    let value = format!(
+        r#"keyId="{}",algorithm="rsa-sha256",headers="{}",signature="{}""#,
+        signer.key_id,
+        covered.join(" "),
+        STANDARD.encode(signature)
+    );
+    headers.insert(
+        HeaderName::from_static("signature"),
+        to_header_value(&value)?,
+    );
+    Ok(headers)
+}
+
+fn to_header_value(value: &str) -> Result<HeaderValue, SignatureError> {
+    HeaderValue::from_str(value).map_err(|_| SignatureError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::pkcs8::EncodePublicKey;
+    use rsa::pkcs8::{EncodePrivateKey, LineEnding};
+
+    use super::*;
+
+    const INBOX: &str = "https://fediboard.example/boards/a/inbox";
+    const BODY: &[u8] = br#"{"type":"Create"}"#;
+
+    fn rsa_keys() -> (Signer, String) {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let public_key_pem = rsa::pkcs8::EncodePublicKey::to_public_key_pem(
+            &private_key.to_public_key(),
+            LineEnding::LF,
+        )
+        .unwrap();
+        let signer = Signer {
+            key_id: "https://remote.example/users/a#main-key".to_owned(),
+            private_key_pem: private_key
+                .to_pkcs8_pem(LineEnding::LF)
+                .unwrap()
+                .to_string(),
+        };
+        (signer, public_key_pem)
+    }
+
+    fn signed_headers(signer: &Signer) -> HeaderMap {
+        sign_request(
+            &Method::POST,
+            &Url::parse(INBOX).unwrap(),
+            Some(BODY),
+            signer,
+        )
+        .unwrap_or_else(|_| panic!("signing failed"))
+    }
+
+    fn verify(
+        headers: &HeaderMap,
+        body: &[u8],
+        public_key_pem: &str,
+    ) -> Result<(), SignatureError> {
+        let params = signature_params(headers)?;
+        check_coverage(headers, &params, true, Utc::now())?;
+        verify_digest(headers, body)?;
+        let signing_string = signing_string(&Method::POST, "/boards/a/inbox", headers, &params)
+            .ok_or(SignatureError::Incomplete)?;
+        if verify_signature(&params, &signing_string, public_key_pem) {
+            Ok(())
+        } else {
+            Err(SignatureError::Invalid)
+        }
+    }
+
+    fn params_covering(headers: &str) -> SignatureParams {
+        parse_signature(&format!(
+            r#"keyId="https://remote.example/users/a#main-key",headers="{}",signature="AAAA""#,
+            headers
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn verifies_rsa_sha256_signatures() {
+        let (signer, public_key_pem) = rsa_keys();
+        let headers = signed_headers(&signer);
+        assert!(verify(&headers, BODY, &public_key_pem).is_ok());
+
+        let (_, other_key_pem) = rsa_keys();
+        assert!(matches!(
+            verify(&headers, BODY, &other_key_pem),
+            Err(SignatureError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn verifies_hs2019_ed25519_signatures() {
+        use ed25519_dalek::Signer as _;
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let public_key_pem = signing_key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("fediboard.example"));
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        headers.insert(header::DATE, HeaderValue::from_str(&date).unwrap());
+        headers.insert("digest", HeaderValue::from_str(&digest(BODY)).unwrap());
+        let mut params = params_covering("(request-target) host date digest");
+        let signing_string =
+            signing_string(&Method::POST, "/boards/a/inbox", &headers, &params).unwrap();
+        params.signature = signing_key.sign(signing_string.as_bytes()).to_vec();
+        let value = format!(
+            r#"keyId="{}",algorithm="hs2019",headers="{}",signature="{}""#,
+            params.key_id,
+            params.headers.join(" "),
+            STANDARD.encode(&params.signature)
+        );
+        headers.insert("signature", HeaderValue::from_str(&value).unwrap());
+        assert!(verify(&headers, BODY, &public_key_pem).is_ok());
+
+        headers.insert(header::HOST, HeaderValue::from_static("other.example"));
+        assert!(matches!(
+            verify(&headers, BODY, &public_key_pem),
+            Err(SignatureError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_bodies() {
+        let (signer, public_key_pem) = rsa_keys();
+        let headers = signed_headers(&signer);
+        assert!(matches!(
+            verify(&headers, br#"{"type":"Delete"}"#, &public_key_pem),
+            Err(SignatureError::DigestMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_dates_more_than_an_hour_off() {
+        let (signer, _) = rsa_keys();
+        let headers = signed_headers(&signer);
+        let params = signature_params(&headers).unwrap_or_else(|_| panic!("unparsable"));
+        let now = Utc::now();
+        for skew in [TimeDelta::minutes(59), TimeDelta::minutes(-59)] {
+            assert!(check_coverage(&headers, &params, true, now + skew).is_ok());
+        }
+        for skew in [TimeDelta::minutes(61), TimeDelta::minutes(-61)] {
+            assert!(matches!(
+                check_coverage(&headers, &params, true, now + skew),
+                Err(SignatureError::Stale)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_signatures_leaving_the_target_or_digest_out() {
+        let mut headers = HeaderMap::new();
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        headers.insert(header::DATE, HeaderValue::from_str(&date).unwrap());
+        let now = Utc::now();
+        for covered in ["host date digest", "(request-target) host date"] {
+            assert!(matches!(
+                check_coverage(&headers, &params_covering(covered), true, now),
+                Err(SignatureError::Incomplete)
+            ));
+        }
+        let params = params_covering("(request-target) host date");
+        assert!(check_coverage(&headers, &params, false, now).is_ok());
+    }
+
+    #[test]
+    fn parses_quoted_values_with_commas() {
+        let params = parse_signature(
+            r#"keyId="https://remote.example/actor?a=1,b=2#key", algorithm="RSA-SHA256",headers="(request-target) Host date",signature="AAAA""#,
+        )
+        .unwrap();
+        assert_eq!(params.key_id, "https://remote.example/actor?a=1,b=2#key");
+        assert_eq!(params.algorithm.as_deref(), Some("rsa-sha256"));
+        assert_eq!(params.headers, ["(request-target)", "host", "date"]);
+        assert_eq!(params.signature, [0, 0, 0]);
+    }
+
+    #[test]
+    fn reads_the_authorization_form() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static(
+                r#"Signature keyId="https://remote.example/users/a#main-key",headers="(request-target) date",signature="AAAA""#,
+            ),
+        );
+        let params = signature_params(&headers).unwrap_or_else(|_| panic!("unparsable"));
+        assert_eq!(params.key_id, "https://remote.example/users/a#main-key");
+        assert_eq!(params.headers, ["(request-target)", "date"]);
+        assert!(params.algorithm.is_none());
+    }
+}
@@ -2,7 +2,11 @@ use axum::extract::FromRef;
 
 use crate::{
     board::BoardUseCase,
+    federation::{DeliveryUseCase, FederationUseCase},
     infra::{AppState, use_case_registry::UseCaseRegistry},
+    instance::InstanceUseCase,
+    media::MediaUseCase,
+    moderation::ModerationUseCase,
     thread::{PostUseCase, ThreadUseCase},
 };
 
@@ -29,4 +33,24 @@ impl DepenencyInjector {
     pub fn post_use_case(&self) -> impl PostUseCase {
         self.use_case_registry.post_use_case()
     }
+
+    pub fn media_use_case(&self) -> impl MediaUseCase {
+        self.use_case_registry.media_use_case()
+    }
+
+    pub fn moderation_use_case(&self) -> impl ModerationUseCase {
+        self.use_case_registry.moderation_use_case()
+    }
+
+    pub fn federation_use_case(&self) -> impl FederationUseCase {
+        self.use_case_registry.federation_use_case()
+    }
+
+    pub fn delivery_use_case(&self) -> impl DeliveryUseCase {
+        self.use_case_registry.delivery_use_case()
+    }
+
+    pub fn instance_use_case(&self) -> impl InstanceUseCase {
+        self.use_case_registry.instance_use_case()
+    }
 }
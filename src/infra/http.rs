@@ -1,7 +1,8 @@
-use crate::infra::{AppState, routing};
+use crate::infra::{AppState, activitypub::delivery_worker, routing};
 
 pub async fn serve(app_state: AppState) -> () {
     let port = app_state.port.clone();
+    delivery_worker::spawn_delivery_worker(app_state.clone());
     let app_routes = routing::build_routes(app_state);
     let addr = format!("0.0.0.0:{}", port);
     println!("Serving at http://{}", addr);
@@ -1,5 +1,15 @@
 mod board_persistence;
+mod delivery_persistence;
+mod federation_persistence;
+mod instance_persistence;
+mod moderation_persistence;
+mod remote_actor_persistence;
 mod thread_persistence;
 
 pub use board_persistence::BoardPgPersistence;
-pub use thread_persistence::ThreadPgPersistence;
\ No newline at end of file
+pub use delivery_persistence::DeliveryPgPersistence;
+pub use federation_persistence::FederationPgPersistence;
+pub use instance_persistence::InstancePgPersistence;
+pub use moderation_persistence::ModerationPgPersistence;
+pub use remote_actor_persistence::RemoteActorPgPersistence;
+pub use thread_persistence::ThreadPgPersistence;
@@ -3,8 +3,14 @@ mod query;
 use crate::board;
 use crate::board::Board;
 use crate::board::BoardError;
+use crate::board::BoardKeys;
 use crate::board::BoardPersistence;
+use crate::board::BoardSettings;
+use crate::board::BoardSettingsUpdate;
+use crate::board::Mirror;
+use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use sqlx::types::Json;
 use uuid::Uuid;
 
 pub struct BoardPgPersistence {
@@ -38,11 +44,86 @@ impl BoardPersistence for BoardPgPersistence {
             Err(e) => Err(map_error(e)),
         }
     }
+
+    async fn find_board_keys(&self, board_id: Uuid) -> Result<Option<BoardKeys>, BoardError> {
+        let fetch_result = query::board_keys_query(board_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_keys(schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn insert_board_keys(
+        &self,
+        board_id: Uuid,
+        keys: &BoardKeys,
+    ) -> Result<BoardKeys, BoardError> {
+        let fetch_result = query::insert_board_keys_query(board_id, keys)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result.map(to_keys) {
+            Ok(Some(keys)) => Ok(keys),
+            Ok(None) => Err(BoardError::DbError),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn insert_board(
+        &self,
+        board_name: &str,
+        mirror: Option<&Mirror>,
+    ) -> Result<Board, BoardError> {
+        let fetch_result = query::insert_board_query(board_name, mirror)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_board(&schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn delete_board(&self, board_id: Uuid) -> Result<(), BoardError> {
+        match query::delete_board_query(board_id)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn update_board_settings(
+        &self,
+        board_id: Uuid,
+        update: &BoardSettingsUpdate,
+    ) -> Result<Board, BoardError> {
+        let changes = Json(to_settings_schema(update));
+        let fetch_result = query::update_board_settings_query(board_id, &changes)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_board(&schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn update_mirror_accepted(&self, board_id: Uuid) -> Result<(), BoardError> {
+        match query::update_mirror_accepted_query(board_id)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
 }
 
 fn map_error(e: sqlx::Error) -> BoardError {
     match e {
         sqlx::Error::RowNotFound => BoardError::NotFound,
+        sqlx::Error::Database(e) if e.is_unique_violation() => BoardError::AlreadyExists,
         _ => BoardError::DbError,
     }
 }
@@ -51,6 +132,48 @@ fn to_board(schema: &BoardSchema) -> Board {
     Board {
         board_id: schema.board_id,
         name: schema.name.to_owned(),
+        settings: to_settings(&schema.settings),
+        mirror: schema.mirror_actor_id.as_ref().map(|actor_id| Mirror {
+            actor_id: actor_id.to_owned(),
+            accepted: schema.mirror_accepted,
+        }),
+    }
+}
+
+fn to_keys(schema: BoardKeysSchema) -> Option<BoardKeys> {
+    Some(BoardKeys {
+        public_key_pem: schema.public_key?,
+        private_key_pem: schema.private_key?,
+    })
+}
+
+fn to_settings(schema: &BoardSettingsSchema) -> BoardSettings {
+    let defaults = BoardSettings::default();
+    BoardSettings {
+        max_file_size: schema.max_file_size.unwrap_or(defaults.max_file_size),
+        max_files_per_post: schema
+            .max_files_per_post
+            .unwrap_or(defaults.max_files_per_post),
+        allowed_mime_types: schema
+            .allowed_mime_types
+            .clone()
+            .unwrap_or(defaults.allowed_mime_types),
+        keep_metadata: schema.keep_metadata.unwrap_or(defaults.keep_metadata),
+        reject_duplicate_files: schema
+            .reject_duplicate_files
+            .unwrap_or(defaults.reject_duplicate_files),
+        spoilers_enabled: schema.spoilers_enabled.unwrap_or(defaults.spoilers_enabled),
+    }
+}
+
+fn to_settings_schema(update: &BoardSettingsUpdate) -> BoardSettingsSchema {
+    BoardSettingsSchema {
+        max_file_size: update.max_file_size,
+        max_files_per_post: update.max_files_per_post,
+        allowed_mime_types: update.allowed_mime_types.clone(),
+        keep_metadata: update.keep_metadata,
+        reject_duplicate_files: update.reject_duplicate_files,
+        spoilers_enabled: update.spoilers_enabled,
     }
 }
 
@@ -58,4 +181,31 @@ fn to_board(schema: &BoardSchema) -> Board {
 struct BoardSchema {
     board_id: Uuid,
     name: String,
+    settings: Json<BoardSettingsSchema>,
+    mirror_actor_id: Option<String>,
+    mirror_accepted: bool,
+}
+
+#[derive(FromRow)]
+struct BoardKeysSchema {
+    public_key: Option<String>,
+    private_key: Option<String>,
+}
+
+/// Unset keys fall back to the defaults in [BoardSettings].
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct BoardSettingsSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_file_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_files_per_post: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_mime_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_metadata: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reject_duplicate_files: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spoilers_enabled: Option<bool>,
 }
@@ -1,13 +1,19 @@
-use super::BoardSchema;
+use super::{BoardKeysSchema, BoardSchema, BoardSettingsSchema};
+use crate::board::{BoardKeys, Mirror};
 use sqlx::Postgres;
 use sqlx::postgres::PgArguments;
+use sqlx::types::Json;
+use uuid::Uuid;
 
 pub(super) type BoardQuery<'q> = sqlx::query::QueryAs<'q, Postgres, BoardSchema, PgArguments>;
+pub(super) type BoardKeysQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, BoardKeysSchema, PgArguments>;
+pub(super) type BoardStatement<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
 
 pub(super) fn all_boards_query() -> BoardQuery<'static> {
     sqlx::query_as::<_, BoardSchema>(
         r#"
-            select board_id, name
+            select board_id, name, settings, mirror_actor_id, mirror_accepted
             from board
         "#,
     )
@@ -16,10 +22,89 @@ pub(super) fn all_boards_query() -> BoardQuery<'static> {
 pub(super) fn board_by_name_query(board_name: &str) -> BoardQuery<'_> {
     sqlx::query_as::<_, BoardSchema>(
         r#"
-            select board_id, name
+            select board_id, name, settings, mirror_actor_id, mirror_accepted
             from board
             where $1 = name
         "#,
     )
     .bind(board_name)
 }
+
+pub(super) fn board_keys_query(board_id: Uuid) -> BoardKeysQuery<'static> {
+    sqlx::query_as::<_, BoardKeysSchema>(
+        r#"
+            select public_key, private_key
+            from board
+            where $1 = board_id
+        "#,
+    )
+    .bind(board_id)
+}
+
+pub(super) fn insert_board_keys_query(board_id: Uuid, keys: &BoardKeys) -> BoardKeysQuery<'_> {
+    sqlx::query_as::<_, BoardKeysSchema>(
+        r#"
+            update board
+            set public_key = case when private_key is null then $2 else public_key end,
+                private_key = coalesce(private_key, $3)
+            where $1 = board_id
+            returning public_key, private_key
+        "#,
+    )
+    .bind(board_id)
+    .bind(&keys.public_key_pem)
+    .bind(&keys.private_key_pem)
+}
+
+pub(super) fn insert_board_query<'q>(
+    board_name: &'q str,
+    mirror: Option<&'q Mirror>,
+) -> BoardQuery<'q> {
+    sqlx::query_as::<_, BoardSchema>(
+        r#"
+            insert into board (name, mirror_actor_id)
+            values ($1, $2)
+            returning board_id, name, settings, mirror_actor_id, mirror_accepted
+        "#,
+    )
+    .bind(board_name)
+    .bind(mirror.map(|mirror| &mirror.actor_id))
+}
+
+pub(super) fn delete_board_query(board_id: Uuid) -> BoardStatement<'static> {
+    sqlx::query(
+        r#"
+            delete from board
+            where $1 = board_id
+        "#,
+    )
+    .bind(board_id)
+}
+
+/// Merges the changed keys into the stored settings, keys not in `changes` are kept.
+pub(super) fn update_board_settings_query(
+    board_id: Uuid,
+    changes: &Json<BoardSettingsSchema>,
+) -> BoardQuery<'_> {
+    sqlx::query_as::<_, BoardSchema>(
+        r#"
+            update board
+            set settings = settings || $2
+            where $1 = board_id
+            returning board_id, name, settings, mirror_actor_id, mirror_accepted
+        "#,
+    )
+    .bind(board_id)
+    .bind(changes)
+}
+
+pub(super) fn update_mirror_accepted_query(board_id: Uuid) -> BoardStatement<'static> {
+    sqlx::query(
+        r#"
+            update board
+            set mirror_accepted = true
+            where $1 = board_id
+        "#,
+    )
+    .bind(board_id)
+}
@@ -0,0 +1,148 @@
+mod query;
+
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+use crate::{
+    board::Board,
+    federation::{DeadInbox, Delivery, DeliveryError, DeliveryPersistence},
+};
+
+pub struct DeliveryPgPersistence {
+    db_pool: sqlx::Pool<sqlx::Postgres>,
+}
+
+impl DeliveryPgPersistence {
+    pub fn new(db_pool: sqlx::Pool<sqlx::Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl DeliveryPersistence for DeliveryPgPersistence {
+    async fn insert_deliveries(
+        &self,
+        board: &Board,
+        inboxes: &[String],
+        activity: &str,
+    ) -> Result<(), DeliveryError> {
+        match query::insert_deliveries_query(&board.board_id, inboxes, activity)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        leased_until: DateTime<Utc>,
+    ) -> Result<Vec<Delivery>, DeliveryError> {
+        let fetch_result = query::claim_due_deliveries_query(limit, leased_until)
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(deliveries) => Ok(deliveries.into_iter().map(to_delivery).collect()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn delete_delivery(&self, delivery_id: &Uuid) -> Result<(), DeliveryError> {
+        match query::delete_delivery_query(delivery_id)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn reschedule_delivery(
+        &self,
+        delivery_id: &Uuid,
+        attempts: i32,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), DeliveryError> {
+        match query::reschedule_delivery_query(delivery_id, attempts, next_attempt_at)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn insert_dead_inbox(&self, inbox: &str) -> Result<(), DeliveryError> {
+        match query::insert_dead_inbox_query(inbox)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn find_dead_inboxes(&self) -> Result<Vec<DeadInbox>, DeliveryError> {
+        let fetch_result = query::find_dead_inboxes_query()
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(dead_inboxes) => Ok(dead_inboxes.into_iter().map(to_dead_inbox).collect()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn find_dead_hosts(&self) -> Result<Vec<String>, DeliveryError> {
+        let fetch_result = query::find_dead_hosts_query()
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(hosts) => Ok(hosts.into_iter().map(|(host,)| host).collect()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+
+    async fn delete_dead_inboxes(&self, host: &str) -> Result<(), DeliveryError> {
+        match query::delete_dead_inboxes_query(host)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(DeliveryError::DbError),
+        }
+    }
+}
+
+fn to_delivery(schema: DeliverySchema) -> Delivery {
+    Delivery {
+        delivery_id: schema.delivery_id,
+        board_name: schema.board_name,
+        inbox: schema.inbox,
+        activity: schema.activity,
+        attempts: schema.attempts,
+    }
+}
+
+fn to_dead_inbox(schema: DeadInboxSchema) -> DeadInbox {
+    DeadInbox {
+        inbox: schema.inbox,
+        dead_since: schema.dead_since,
+    }
+}
+
+#[derive(FromRow)]
+struct DeliverySchema {
+    delivery_id: Uuid,
+    board_name: String,
+    inbox: String,
+    activity: String,
+    attempts: i32,
+}
+
+#[derive(FromRow)]
+struct DeadInboxSchema {
+    inbox: String,
+    dead_since: DateTime<Utc>,
+}
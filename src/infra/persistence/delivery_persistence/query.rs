@@ -0,0 +1,131 @@
+use super::{DeadInboxSchema, DeliverySchema};
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+use uuid::Uuid;
+
+pub(super) type DeliveryQuery<'q> = sqlx::query::QueryAs<'q, Postgres, DeliverySchema, PgArguments>;
+
+pub(super) type DeadInboxQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, DeadInboxSchema, PgArguments>;
+
+pub(super) type HostQuery<'q> = sqlx::query::QueryAs<'q, Postgres, (String,), PgArguments>;
+
+pub(super) type DeliveryStatement<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+pub(super) fn insert_deliveries_query<'q>(
+    board_id: &'q Uuid,
+    inboxes: &'q [String],
+    activity: &'q str,
+) -> DeliveryStatement<'q> {
+    sqlx::query(
+        r#"
+            insert into delivery (board_id, inbox, activity)
+            select $1, target.inbox, $3::jsonb
+            from unnest($2::text[]) as target (inbox)
+            where not exists (
+                select 1 from dead_inbox where dead_inbox.inbox = target.inbox
+            )
+        "#,
+    )
+    .bind(board_id)
+    .bind(inboxes)
+    .bind(activity)
+}
+
+/// Concurrent workers skip the rows another one has locked.
+pub(super) fn claim_due_deliveries_query(
+    limit: i64,
+    leased_until: DateTime<Utc>,
+) -> DeliveryQuery<'static> {
+    sqlx::query_as::<_, DeliverySchema>(
+        r#"
+            update delivery
+            set next_attempt_at = $2
+            from board
+            where delivery.delivery_id in (
+                select delivery_id from delivery
+                where next_attempt_at <= now()
+                order by next_attempt_at
+                limit $1
+                for update skip locked
+            )
+            and board.board_id = delivery.board_id
+            returning delivery.delivery_id, board.name as board_name, delivery.inbox,
+                delivery.activity::text as activity, delivery.attempts
+        "#,
+    )
+    .bind(limit)
+    .bind(leased_until)
+}
+
+pub(super) fn delete_delivery_query(delivery_id: &Uuid) -> DeliveryStatement<'_> {
+    sqlx::query(
+        r#"
+            delete from delivery
+            where delivery_id = $1
+        "#,
+    )
+    .bind(delivery_id)
+}
+
+pub(super) fn reschedule_delivery_query(
+    delivery_id: &Uuid,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+) -> DeliveryStatement<'_> {
+    sqlx::query(
+        r#"
+            update delivery
+            set attempts = $2, next_attempt_at = $3
+            where delivery_id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(attempts)
+    .bind(next_attempt_at)
+}
+
+pub(super) fn insert_dead_inbox_query(inbox: &str) -> DeliveryStatement<'_> {
+    sqlx::query(
+        r#"
+            with dead as (
+                insert into dead_inbox (inbox) values ($1)
+                on conflict do nothing
+            )
+            delete from delivery
+            where inbox = $1
+        "#,
+    )
+    .bind(inbox)
+}
+
+pub(super) fn find_dead_inboxes_query() -> DeadInboxQuery<'static> {
+    sqlx::query_as::<_, DeadInboxSchema>(
+        r#"
+            select inbox, dead_since
+            from dead_inbox
+            order by dead_since desc
+        "#,
+    )
+}
+
+pub(super) fn find_dead_hosts_query() -> HostQuery<'static> {
+    sqlx::query_as::<_, (String,)>(
+        r#"
+            select distinct host
+            from dead_inbox
+            where host is not null
+        "#,
+    )
+}
+
+pub(super) fn delete_dead_inboxes_query(host: &str) -> DeliveryStatement<'_> {
+    sqlx::query(
+        r#"
+            delete from dead_inbox
+            where host = $1
+        "#,
+    )
+    .bind(host)
+}
@@ -0,0 +1,86 @@
+mod query;
+
+use sqlx::prelude::FromRow;
+
+use crate::{
+    board::Board,
+    federation::{FederationError, FederationPersistence, Follower},
+};
+
+pub struct FederationPgPersistence {
+    db_pool: sqlx::Pool<sqlx::Postgres>,
+}
+
+impl FederationPgPersistence {
+    pub fn new(db_pool: sqlx::Pool<sqlx::Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl FederationPersistence for FederationPgPersistence {
+    async fn upsert_follower(
+        &self,
+        board: &Board,
+        follower: &Follower,
+    ) -> Result<(), FederationError> {
+        match query::upsert_follower_query(&board.board_id, follower)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(FederationError::DbError),
+        }
+    }
+
+    async fn delete_follower(
+        &self,
+        board: &Board,
+        actor_id: &str,
+        follow_id: Option<&str>,
+    ) -> Result<(), FederationError> {
+        match query::delete_follower_query(&board.board_id, actor_id, follow_id)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(FederationError::DbError),
+        }
+    }
+
+    async fn find_followers(&self, board: &Board) -> Result<Vec<Follower>, FederationError> {
+        let fetch_result = query::followers_query(&board.board_id)
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(followers) => Ok(followers.into_iter().map(to_follower).collect()),
+            Err(_) => Err(FederationError::DbError),
+        }
+    }
+
+    async fn count_followers(&self, board: &Board) -> Result<i64, FederationError> {
+        let fetch_result = query::follower_count_query(&board.board_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok((count,)) => Ok(count),
+            Err(_) => Err(FederationError::DbError),
+        }
+    }
+}
+
+fn to_follower(schema: FollowerSchema) -> Follower {
+    Follower {
+        actor_id: schema.actor_id,
+        inbox: schema.inbox,
+        shared_inbox: schema.shared_inbox,
+        follow_id: schema.follow_id,
+    }
+}
+
+#[derive(FromRow)]
+struct FollowerSchema {
+    actor_id: String,
+    inbox: String,
+    shared_inbox: Option<String>,
+    follow_id: Option<String>,
+}
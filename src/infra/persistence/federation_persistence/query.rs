@@ -0,0 +1,71 @@
+use super::FollowerSchema;
+use crate::federation::Follower;
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+use uuid::Uuid;
+
+pub(super) type FollowerQuery<'q> = sqlx::query::QueryAs<'q, Postgres, FollowerSchema, PgArguments>;
+
+pub(super) type CountQuery<'q> = sqlx::query::QueryAs<'q, Postgres, (i64,), PgArguments>;
+
+pub(super) type FederationStatement<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+pub(super) fn upsert_follower_query<'q>(
+    board_id: &'q Uuid,
+    follower: &'q Follower,
+) -> FederationStatement<'q> {
+    sqlx::query(
+        r#"
+            insert into follower (board_id, actor_id, inbox, shared_inbox, follow_id)
+            values ($1, $2, $3, $4, $5)
+            on conflict (board_id, actor_id)
+            do update set inbox = excluded.inbox, shared_inbox = excluded.shared_inbox,
+                follow_id = excluded.follow_id
+        "#,
+    )
+    .bind(board_id)
+    .bind(&follower.actor_id)
+    .bind(&follower.inbox)
+    .bind(&follower.shared_inbox)
+    .bind(&follower.follow_id)
+}
+
+pub(super) fn delete_follower_query<'q>(
+    board_id: &'q Uuid,
+    actor_id: &'q str,
+    follow_id: Option<&'q str>,
+) -> FederationStatement<'q> {
+    sqlx::query(
+        r#"
+            delete from follower
+            where board_id = $1 and actor_id = $2
+            and ($3::text is null or follow_id = $3)
+        "#,
+    )
+    .bind(board_id)
+    .bind(actor_id)
+    .bind(follow_id)
+}
+
+pub(super) fn followers_query(board_id: &Uuid) -> FollowerQuery<'_> {
+    sqlx::query_as::<_, FollowerSchema>(
+        r#"
+            select actor_id, inbox, shared_inbox, follow_id
+            from follower
+            where board_id = $1
+            order by created_at
+        "#,
+    )
+    .bind(board_id)
+}
+
+pub(super) fn follower_count_query(board_id: &Uuid) -> CountQuery<'_> {
+    sqlx::query_as::<_, (i64,)>(
+        r#"
+            select count(*)
+            from follower
+            where board_id = $1
+        "#,
+    )
+    .bind(board_id)
+}
@@ -0,0 +1,36 @@
+mod query;
+
+use sqlx::prelude::FromRow;
+
+use crate::instance::{InstanceError, InstancePersistence, InstanceUsage};
+
+pub struct InstancePgPersistence {
+    db_pool: sqlx::Pool<sqlx::Postgres>,
+}
+
+impl InstancePgPersistence {
+    pub fn new(db_pool: sqlx::Pool<sqlx::Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl InstancePersistence for InstancePgPersistence {
+    async fn count_usage(&self) -> Result<InstanceUsage, InstanceError> {
+        let fetch_result = query::usage_query().fetch_one(&self.db_pool).await;
+        match fetch_result {
+            Ok(schema) => Ok(InstanceUsage {
+                boards: schema.boards,
+                threads: schema.threads,
+                posts: schema.posts,
+            }),
+            Err(_) => Err(InstanceError::DbError),
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct UsageSchema {
+    boards: i64,
+    threads: i64,
+    posts: i64,
+}
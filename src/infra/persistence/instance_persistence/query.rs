@@ -0,0 +1,20 @@
+use super::UsageSchema;
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+
+pub(super) type UsageQuery<'q> = sqlx::query::QueryAs<'q, Postgres, UsageSchema, PgArguments>;
+
+pub(super) fn usage_query() -> UsageQuery<'static> {
+    sqlx::query_as::<_, UsageSchema>(
+        r#"
+            select
+                (select count(*) from board) as boards,
+                (select count(*) from thread) as threads,
+                (
+                    select count(*)
+                    from thread, jsonb_array_elements(posts -> 'posts') as post
+                    where jsonb_typeof(post -> 'remote') is distinct from 'object'
+                ) as posts
+        "#,
+    )
+}
@@ -0,0 +1,151 @@
+mod query;
+
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+
+use crate::moderation::{BannedHash, DomainPolicy, ModerationError, ModerationPersistence, Policy};
+
+pub struct ModerationPgPersistence {
+    db_pool: sqlx::Pool<sqlx::Postgres>,
+}
+
+impl ModerationPgPersistence {
+    pub fn new(db_pool: sqlx::Pool<sqlx::Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl ModerationPersistence for ModerationPgPersistence {
+    async fn find_banned_hash(&self, hash: &str) -> Result<BannedHash, ModerationError> {
+        let fetch_result = query::banned_hash_query(hash)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_banned_hash(schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn find_all_banned_hashes(&self) -> Result<Vec<BannedHash>, ModerationError> {
+        let fetch_result = query::all_banned_hashes_query()
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(hashes) => Ok(hashes.into_iter().map(to_banned_hash).collect()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn insert_banned_hashes(
+        &self,
+        hashes: &[String],
+        reason: &str,
+    ) -> Result<u64, ModerationError> {
+        match query::insert_banned_hashes_query(hashes, reason)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(result) => Ok(result.rows_affected()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn delete_banned_hash(&self, hash: &str) -> Result<(), ModerationError> {
+        match query::delete_banned_hash_query(hash)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => Err(ModerationError::NotFound),
+            Ok(_) => Ok(()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn find_domain_policies(
+        &self,
+        domains: &[String],
+    ) -> Result<Vec<DomainPolicy>, ModerationError> {
+        let fetch_result = query::domain_policies_query(domains)
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(policies) => Ok(policies.into_iter().filter_map(to_domain_policy).collect()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn find_all_domain_policies(&self) -> Result<Vec<DomainPolicy>, ModerationError> {
+        let fetch_result = query::all_domain_policies_query()
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(policies) => Ok(policies.into_iter().filter_map(to_domain_policy).collect()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn upsert_domain_policy(
+        &self,
+        domain: &str,
+        policy: Policy,
+        reason: &str,
+    ) -> Result<DomainPolicy, ModerationError> {
+        let fetch_result = query::upsert_domain_policy_query(domain, policy.as_str(), reason)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => to_domain_policy(schema).ok_or(ModerationError::DbError),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn delete_domain_policy(&self, domain: &str) -> Result<(), ModerationError> {
+        match query::delete_domain_policy_query(domain)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => Err(ModerationError::NotFound),
+            Ok(_) => Ok(()),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+}
+
+fn map_error(e: sqlx::Error) -> ModerationError {
+    match e {
+        sqlx::Error::RowNotFound => ModerationError::NotFound,
+        _ => ModerationError::DbError,
+    }
+}
+
+fn to_banned_hash(schema: BannedHashSchema) -> BannedHash {
+    BannedHash {
+        hash: schema.hash,
+        reason: schema.reason,
+        created_at: schema.created_at,
+    }
+}
+
+#[derive(FromRow)]
+struct BannedHashSchema {
+    hash: String,
+    reason: String,
+    created_at: DateTime<Utc>,
+}
+
+fn to_domain_policy(schema: DomainPolicySchema) -> Option<DomainPolicy> {
+    Some(DomainPolicy {
+        domain: schema.domain,
+        policy: schema.policy.parse().ok()?,
+        reason: schema.reason,
+        created_at: schema.created_at,
+    })
+}
+
+#[derive(FromRow)]
+struct DomainPolicySchema {
+    domain: String,
+    policy: String,
+    reason: String,
+    created_at: DateTime<Utc>,
+}
@@ -0,0 +1,107 @@
+use super::{BannedHashSchema, DomainPolicySchema};
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+
+pub(super) type BannedHashQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, BannedHashSchema, PgArguments>;
+
+pub(super) type DomainPolicyQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, DomainPolicySchema, PgArguments>;
+
+pub(super) type ModerationStatement<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+pub(super) fn all_banned_hashes_query() -> BannedHashQuery<'static> {
+    sqlx::query_as::<_, BannedHashSchema>(
+        r#"
+            select hash, reason, created_at
+            from banned_hash
+            order by created_at desc
+        "#,
+    )
+}
+
+pub(super) fn banned_hash_query(hash: &str) -> BannedHashQuery<'_> {
+    sqlx::query_as::<_, BannedHashSchema>(
+        r#"
+            select hash, reason, created_at
+            from banned_hash
+            where hash = $1
+        "#,
+    )
+    .bind(hash)
+}
+
+pub(super) fn insert_banned_hashes_query<'q>(
+    hashes: &'q [String],
+    reason: &'q str,
+) -> ModerationStatement<'q> {
+    sqlx::query(
+        r#"
+            insert into banned_hash (hash, reason)
+            select unnest($1::text[]), $2
+            on conflict (hash) do nothing
+        "#,
+    )
+    .bind(hashes)
+    .bind(reason)
+}
+
+pub(super) fn delete_banned_hash_query(hash: &str) -> ModerationStatement<'_> {
+    sqlx::query(
+        r#"
+            delete from banned_hash
+            where hash = $1
+        "#,
+    )
+    .bind(hash)
+}
+
+pub(super) fn domain_policies_query(domains: &[String]) -> DomainPolicyQuery<'_> {
+    sqlx::query_as::<_, DomainPolicySchema>(
+        r#"
+            select domain, policy, reason, created_at
+            from domain_policy
+            where domain = any($1)
+        "#,
+    )
+    .bind(domains)
+}
+
+pub(super) fn all_domain_policies_query() -> DomainPolicyQuery<'static> {
+    sqlx::query_as::<_, DomainPolicySchema>(
+        r#"
+            select domain, policy, reason, created_at
+            from domain_policy
+            order by domain
+        "#,
+    )
+}
+
+pub(super) fn upsert_domain_policy_query<'q>(
+    domain: &'q str,
+    policy: &'q str,
+    reason: &'q str,
+) -> DomainPolicyQuery<'q> {
+    sqlx::query_as::<_, DomainPolicySchema>(
+        r#"
+            insert into domain_policy (domain, policy, reason)
+            values ($1, $2, $3)
+            on conflict (domain)
+            do update set policy = excluded.policy, reason = excluded.reason
+            returning domain, policy, reason, created_at
+        "#,
+    )
+    .bind(domain)
+    .bind(policy)
+    .bind(reason)
+}
+
+pub(super) fn delete_domain_policy_query(domain: &str) -> ModerationStatement<'_> {
+    sqlx::query(
+        r#"
+            delete from domain_policy
+            where domain = $1
+        "#,
+    )
+    .bind(domain)
+}
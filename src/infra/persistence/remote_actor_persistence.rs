@@ -0,0 +1,84 @@
+mod query;
+
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+
+use crate::federation::{RemoteActor, RemoteActorError, RemoteActorPersistence};
+
+#[derive(Clone)]
+pub struct RemoteActorPgPersistence {
+    db_pool: sqlx::Pool<sqlx::Postgres>,
+}
+
+impl RemoteActorPgPersistence {
+    pub fn new(db_pool: sqlx::Pool<sqlx::Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl RemoteActorPersistence for RemoteActorPgPersistence {
+    async fn find_remote_actor(&self, actor_id: &str) -> Result<RemoteActor, RemoteActorError> {
+        let fetch_result = query::remote_actor_query(actor_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_remote_actor(schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn find_remote_actor_by_key(
+        &self,
+        key_id: &str,
+    ) -> Result<RemoteActor, RemoteActorError> {
+        let fetch_result = query::remote_actor_by_key_query(key_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(schema) => Ok(to_remote_actor(schema)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    async fn upsert_remote_actor(&self, actor: &RemoteActor) -> Result<(), RemoteActorError> {
+        match query::upsert_remote_actor_query(actor)
+            .execute(&self.db_pool)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(RemoteActorError::DbError),
+        }
+    }
+}
+
+fn map_error(error: sqlx::Error) -> RemoteActorError {
+    match error {
+        sqlx::Error::RowNotFound => RemoteActorError::NotFound,
+        _ => RemoteActorError::DbError,
+    }
+}
+
+fn to_remote_actor(schema: RemoteActorSchema) -> RemoteActor {
+    RemoteActor {
+        actor_id: schema.actor_id,
+        inbox: schema.inbox,
+        shared_inbox: schema.shared_inbox,
+        preferred_username: schema.preferred_username,
+        name: schema.name,
+        key_id: schema.key_id,
+        public_key_pem: schema.public_key_pem,
+        fetched_at: schema.fetched_at,
+    }
+}
+
+#[derive(FromRow)]
+struct RemoteActorSchema {
+    actor_id: String,
+    inbox: String,
+    shared_inbox: Option<String>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    key_id: String,
+    public_key_pem: String,
+    fetched_at: DateTime<Utc>,
+}
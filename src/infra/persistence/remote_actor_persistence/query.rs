@@ -0,0 +1,58 @@
+use super::RemoteActorSchema;
+use crate::federation::RemoteActor;
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+
+pub(super) type RemoteActorQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, RemoteActorSchema, PgArguments>;
+
+pub(super) type RemoteActorStatement<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+pub(super) fn remote_actor_query(actor_id: &str) -> RemoteActorQuery<'_> {
+    sqlx::query_as::<_, RemoteActorSchema>(
+        r#"
+            select actor_id, inbox, shared_inbox, preferred_username, name, key_id,
+                public_key_pem, fetched_at
+            from remote_actor
+            where actor_id = $1
+        "#,
+    )
+    .bind(actor_id)
+}
+
+pub(super) fn remote_actor_by_key_query(key_id: &str) -> RemoteActorQuery<'_> {
+    sqlx::query_as::<_, RemoteActorSchema>(
+        r#"
+            select actor_id, inbox, shared_inbox, preferred_username, name, key_id,
+                public_key_pem, fetched_at
+            from remote_actor
+            where key_id = $1
+            order by fetched_at desc
+            limit 1
+        "#,
+    )
+    .bind(key_id)
+}
+
+pub(super) fn upsert_remote_actor_query(actor: &RemoteActor) -> RemoteActorStatement<'_> {
+    sqlx::query(
+        r#"
+            insert into remote_actor (actor_id, inbox, shared_inbox, preferred_username, name,
+                key_id, public_key_pem, fetched_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            on conflict (actor_id)
+            do update set inbox = excluded.inbox, shared_inbox = excluded.shared_inbox,
+                preferred_username = excluded.preferred_username, name = excluded.name,
+                key_id = excluded.key_id, public_key_pem = excluded.public_key_pem,
+                fetched_at = excluded.fetched_at
+        "#,
+    )
+    .bind(&actor.actor_id)
+    .bind(&actor.inbox)
+    .bind(&actor.shared_inbox)
+    .bind(&actor.preferred_username)
+    .bind(&actor.name)
+    .bind(&actor.key_id)
+    .bind(&actor.public_key_pem)
+    .bind(actor.fetched_at)
+}
@@ -1,10 +1,14 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Error, prelude::FromRow, types::Json};
+use sqlx::{Error, Postgres, Transaction, prelude::FromRow, types::Json};
 use uuid::Uuid;
 
 use crate::{
     board::Board,
-    thread::{Post, Posts, Thread, ThreadError, ThreadPersistence},
+    thread::{
+        Attachment, Post, Posts, Reaction, ReactionKind, RemoteOrigin, Thread, ThreadError,
+        ThreadPersistence,
+    },
 };
 
 mod query;
@@ -46,56 +50,217 @@ impl ThreadPersistence for ThreadPgPersistence {
         }
     }
 
+    async fn count_local_posts(&self, board: &Board) -> Result<i64, ThreadError> {
+        let fetch_result = query::count_local_posts_query(&board.board_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok((count,)) => Ok(count),
+            Err(_) => Err(ThreadError::DbError),
+        }
+    }
+
+    async fn find_local_posts(
+        &self,
+        board: &Board,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(Thread, Post)>, ThreadError> {
+        let fetch_result = query::local_posts_query(&board.board_id, limit, offset)
+            .fetch_all(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(|row| (to_domain(&row.thread), to_post(&row.post)))
+                .collect()),
+            Err(_) => Err(ThreadError::DbError),
+        }
+    }
+
+    async fn find_thread_by_media_hash(
+        &self,
+        board: &Board,
+        hash: &str,
+    ) -> Result<Thread, ThreadError> {
+        let fetch_result = query::build_by_media_hash_query(&board.board_id, hash)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(thread) => Ok(to_domain(&thread)),
+            Err(err) => Err(map_error(err)),
+        }
+    }
+
+    async fn find_thread_by_remote_object(
+        &self,
+        board: &Board,
+        object_id: &str,
+    ) -> Result<Thread, ThreadError> {
+        let fetch_result = query::build_by_remote_object_query(&board.board_id, object_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(thread) => Ok(to_domain(&thread)),
+            Err(err) => Err(map_error(err)),
+        }
+    }
+
+    async fn find_thread_by_reaction(
+        &self,
+        board: &Board,
+        activity_id: &str,
+    ) -> Result<Thread, ThreadError> {
+        let fetch_result = query::build_by_reaction_query(&board.board_id, activity_id)
+            .fetch_one(&self.db_pool)
+            .await;
+        match fetch_result {
+            Ok(thread) => Ok(to_domain(&thread)),
+            Err(err) => Err(map_error(err)),
+        }
+    }
+
     async fn insert_thread(
         &self,
-        board: crate::board::Board,
+        board: &crate::board::Board,
         thread_creation: crate::thread::ThreadCreation,
     ) -> Result<crate::thread::Thread, crate::thread::ThreadError> {
+        let mut transaction = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        reject_duplicates(&mut transaction, board, &thread_creation.attachments).await?;
         let initial_post = PostSchema {
             id: Uuid::new_v4(),
             name: thread_creation.name,
             subject: thread_creation.subject,
             content: thread_creation.content,
             media_url: thread_creation.media_url,
+            attachments: thread_creation
+                .attachments
+                .iter()
+                .map(to_attachment_schema)
+                .collect(),
+            spoiler: thread_creation.spoiler,
+            remote: thread_creation.remote.as_ref().map(to_remote_schema),
+            published: Some(Utc::now()),
+            edited: None,
+            deleted: None,
+            reactions: Vec::new(),
         };
         let post_ser = Json(PostsSchema {
             posts: vec![initial_post],
         });
-        let create_result = query::build_create_query(board.board_id, &post_ser)
-            .fetch_one(&self.db_pool)
-            .await;
-        match create_result {
-            Ok(created) => Ok(to_domain(&created)),
-            Err(_) => Err(ThreadError::DbError),
-        }
+        let created = query::build_create_query(board.board_id, &post_ser)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        transaction
+            .commit()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        Ok(to_domain(&created))
     }
 
     async fn insert_post(
         &self,
+        board: &Board,
         thread: &Thread,
         post: Post,
     ) -> Result<Thread, crate::thread::ThreadError> {
-        let mut to_update = thread.posts.posts.clone();
-        to_update.push(post);
+        let mut transaction = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        reject_duplicates(&mut transaction, board, &post.attachments).await?;
+        let post_ser = Json(to_post_schema(&post));
+        let thread_schema = query::append_post_query(&post_ser, &thread.thread_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        transaction
+            .commit()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        Ok(to_domain(&thread_schema))
+    }
+
+    async fn update_post(
+        &self,
+        thread_id: &Uuid,
+        change: impl FnOnce(&Thread) -> Option<Post> + Send,
+    ) -> Result<Thread, ThreadError> {
+        let mut transaction = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        let locked = query::build_by_id_for_update_query(thread_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(map_error)?;
+        let thread = to_domain(&locked);
+        let Some(post) = change(&thread) else {
+            return Ok(thread);
+        };
         let update = PostsSchema {
-            posts: to_update
-                .into_iter()
-                .map(|p| PostSchema {
-                    id: p.id,
-                    name: p.name,
-                    subject: p.subject,
-                    content: p.content,
-                    media_url: p.media_url,
+            posts: thread
+                .posts
+                .posts
+                .iter()
+                .map(|p| match p.id == post.id {
+                    true => to_post_schema(&post),
+                    false => to_post_schema(p),
                 })
                 .collect(),
         };
         let update_ser = Json(update);
-        let query_result = query::update_posts_query(&update_ser, &thread.thread_id);
-        match query_result.fetch_one(&self.db_pool).await {
-            Ok(thread_schema) => Ok(to_domain(&thread_schema)),
-            Err(_) => Err(ThreadError::DbError),
+        let updated = query::update_posts_query(&update_ser, thread_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        transaction
+            .commit()
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        Ok(to_domain(&updated))
+    }
+}
+
+/// Locks each file hash on the board until the transaction ends, so concurrent posts of the
+/// same file are checked one after the other and only the first one gets in.
+async fn reject_duplicates(
+    transaction: &mut Transaction<'_, Postgres>,
+    board: &Board,
+    attachments: &[Attachment],
+) -> Result<(), ThreadError> {
+    if !board.settings.reject_duplicate_files {
+        return Ok(());
+    }
+    let mut hashes: Vec<&str> = attachments
+        .iter()
+        .filter_map(|a| a.hash.as_deref())
+        .collect();
+    hashes.sort_unstable(); // always lock in the same order
+    hashes.dedup();
+    for hash in &hashes {
+        query::lock_media_hash_query(&board.board_id, hash)
+            .execute(&mut **transaction)
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+    }
+    for hash in hashes {
+        let original = query::build_by_media_hash_query(&board.board_id, hash)
+            .fetch_optional(&mut **transaction)
+            .await
+            .map_err(|_| ThreadError::DbError)?;
+        if let Some(original) = original {
+            return Err(ThreadError::DuplicateFile(original.thread_id));
         }
     }
+    Ok(())
 }
 
 #[derive(FromRow)]
@@ -105,6 +270,14 @@ struct ThreadSchema {
     posts: Json<PostsSchema>,
 }
 
+/// A post with its thread, which only holds the opening post.
+#[derive(FromRow)]
+struct LocalPostSchema {
+    #[sqlx(flatten)]
+    thread: ThreadSchema,
+    post: Json<PostSchema>,
+}
+
 #[derive(Deserialize, Serialize)]
 struct PostsSchema {
     posts: Vec<PostSchema>,
@@ -117,6 +290,51 @@ pub struct PostSchema {
     pub subject: Option<String>,
     pub content: Option<String>,
     pub media_url: Option<String>,
+    #[serde(default)] // posts written before attachments were tracked
+    pub attachments: Vec<AttachmentSchema>,
+    #[serde(default)]
+    pub spoiler: bool,
+    #[serde(default)]
+    pub remote: Option<RemoteOriginSchema>,
+    #[serde(default)] // posts written before timestamps were kept
+    pub published: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub edited: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deleted: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub reactions: Vec<ReactionSchema>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReactionSchema {
+    pub kind: String,
+    pub actor_id: String,
+    pub activity_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RemoteOriginSchema {
+    pub actor_id: String,
+    pub object_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AttachmentSchema {
+    #[serde(default)]
+    pub hash: Option<String>,
+    pub url: String,
+    pub mime_type: String,
+    pub size: usize,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub thumbnail_url: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub codec: Option<String>,
 }
 
 fn to_domain(thread_schema: &ThreadSchema) -> Thread {
@@ -125,20 +343,114 @@ fn to_domain(thread_schema: &ThreadSchema) -> Thread {
         thread_id: thread_schema.thread_id,
         board_id: thread_schema.board_id,
         posts: Posts {
-            posts: posts
-                .iter()
-                .map(|p| Post {
-                    id: p.id,
-                    name: p.name.clone(),
-                    subject: p.subject.clone(),
-                    content: p.content.clone(),
-                    media_url: p.media_url.clone(),
-                })
-                .collect(), // TODO: simplify
+            posts: posts.iter().map(to_post).collect(),
         },
     }
 }
 
+fn to_post(p: &PostSchema) -> Post {
+    Post {
+        id: p.id,
+        name: p.name.clone(),
+        subject: p.subject.clone(),
+        content: p.content.clone(),
+        media_url: p.media_url.clone(),
+        attachments: p.attachments.iter().map(to_attachment).collect(),
+        spoiler: p.spoiler,
+        remote: p.remote.as_ref().map(to_remote_origin),
+        published: p.published,
+        edited: p.edited,
+        deleted: p.deleted,
+        reactions: p.reactions.iter().filter_map(to_reaction).collect(),
+    }
+}
+
+fn to_attachment(schema: &AttachmentSchema) -> Attachment {
+    Attachment {
+        hash: schema.hash.clone(),
+        url: schema.url.clone(),
+        mime_type: schema.mime_type.clone(),
+        size: schema.size,
+        width: schema.width,
+        height: schema.height,
+        thumbnail_url: schema.thumbnail_url.clone(),
+        duration: schema.duration,
+        codec: schema.codec.clone(),
+    }
+}
+
+fn to_post_schema(post: &Post) -> PostSchema {
+    PostSchema {
+        id: post.id,
+        name: post.name.clone(),
+        subject: post.subject.clone(),
+        content: post.content.clone(),
+        media_url: post.media_url.clone(),
+        attachments: post.attachments.iter().map(to_attachment_schema).collect(),
+        spoiler: post.spoiler,
+        remote: post.remote.as_ref().map(to_remote_schema),
+        published: post.published,
+        edited: post.edited,
+        deleted: post.deleted,
+        reactions: post.reactions.iter().map(to_reaction_schema).collect(),
+    }
+}
+
+fn to_reaction(schema: &ReactionSchema) -> Option<Reaction> {
+    let kind = match schema.kind.as_str() {
+        "Like" => ReactionKind::Like,
+        "Announce" => ReactionKind::Announce,
+        _ => return None,
+    };
+    Some(Reaction {
+        kind,
+        actor_id: schema.actor_id.clone(),
+        activity_id: schema.activity_id.clone(),
+    })
+}
+
+fn to_reaction_schema(reaction: &Reaction) -> ReactionSchema {
+    let kind = match reaction.kind {
+        ReactionKind::Like => "Like",
+        ReactionKind::Announce => "Announce",
+    };
+    ReactionSchema {
+        kind: kind.to_owned(),
+        actor_id: reaction.actor_id.clone(),
+        activity_id: reaction.activity_id.clone(),
+    }
+}
+
+fn to_remote_origin(schema: &RemoteOriginSchema) -> RemoteOrigin {
+    RemoteOrigin {
+        actor_id: schema.actor_id.clone(),
+        object_id: schema.object_id.clone(),
+        activity: schema.activity.clone(),
+    }
+}
+
+fn to_remote_schema(remote: &RemoteOrigin) -> RemoteOriginSchema {
+    RemoteOriginSchema {
+        actor_id: remote.actor_id.clone(),
+        object_id: remote.object_id.clone(),
+        activity: remote.activity.clone(),
+    }
+}
+
+fn to_attachment_schema(attachment: &Attachment) -> AttachmentSchema {
+    AttachmentSchema {
+        hash: attachment.hash.clone(),
+        url: attachment.url.clone(),
+        mime_type: attachment.mime_type.clone(),
+        size: attachment.size,
+        width: attachment.width,
+        height: attachment.height,
+        thumbnail_url: attachment.thumbnail_url.clone(),
+        duration: attachment.duration,
+        codec: attachment.codec.clone(),
+    }
+}
+
 fn map_error(err: sqlx::Error) -> ThreadError {
     match err {
         Error::RowNotFound => ThreadError::NotFound,
@@ -4,10 +4,15 @@ use sqlx::{
     types::{Json, Uuid},
 };
 
-use super::{PostsSchema, ThreadSchema};
+use super::{LocalPostSchema, PostSchema, PostsSchema, ThreadSchema};
 
 pub(super) type ThreadQuery<'q> = sqlx::query::QueryAs<'q, Postgres, ThreadSchema, PgArguments>;
 
+pub(super) type LocalPostQuery<'q> =
+    sqlx::query::QueryAs<'q, Postgres, LocalPostSchema, PgArguments>;
+
+pub(super) type CountQuery<'q> = sqlx::query::QueryAs<'q, Postgres, (i64,), PgArguments>;
+
 pub(super) fn build_create_query<'q>(
     board_id: Uuid,
     post_ser: &'q Json<PostsSchema>,
@@ -33,6 +38,41 @@ pub(super) fn build_by_board_id_query(board_id: &Uuid) -> ThreadQuery<'_> {
     .bind(board_id)
 }
 
+/// Federated replies keep where they came from in `remote`, local posts have it null or unset.
+pub(super) fn count_local_posts_query(board_id: &Uuid) -> CountQuery<'_> {
+    sqlx::query_as::<_, (i64,)>(
+        r#"
+        select count(*) from thread
+        cross join lateral jsonb_array_elements(thread.posts -> 'posts') as post (value)
+        where thread.board_id = $1
+        and jsonb_typeof(post.value -> 'remote') is distinct from 'object'
+        "#,
+    )
+    .bind(board_id)
+}
+
+/// Orders like [count_local_posts_query] counts, later posts of a thread first on equal times.
+pub(super) fn local_posts_query(board_id: &Uuid, limit: i64, offset: i64) -> LocalPostQuery<'_> {
+    sqlx::query_as::<_, LocalPostSchema>(
+        r#"
+        select thread.thread_id, thread.board_id,
+            jsonb_build_object('posts', jsonb_build_array(thread.posts -> 'posts' -> 0)) as posts,
+            post.value as post
+        from thread
+        cross join lateral jsonb_array_elements(thread.posts -> 'posts')
+            with ordinality as post (value, position)
+        where thread.board_id = $1
+        and jsonb_typeof(post.value -> 'remote') is distinct from 'object'
+        order by (post.value ->> 'published')::timestamptz desc nulls last,
+            post.position desc, thread.thread_id
+        limit $2 offset $3
+        "#,
+    )
+    .bind(board_id)
+    .bind(limit)
+    .bind(offset)
+}
+
 pub(super) fn build_by_id_query(thread_id: &Uuid) -> ThreadQuery<'_> {
     sqlx::query_as::<_, ThreadSchema>(
         r#"
@@ -43,6 +83,89 @@ pub(super) fn build_by_id_query(thread_id: &Uuid) -> ThreadQuery<'_> {
     .bind(thread_id)
 }
 
+pub(super) fn build_by_reaction_query<'q>(
+    board_id: &'q Uuid,
+    activity_id: &'q str,
+) -> ThreadQuery<'q> {
+    sqlx::query_as::<_, ThreadSchema>(
+        r#"
+        select * from thread
+        where board_id = $1
+        and posts @> jsonb_build_object(
+            'posts', jsonb_build_array(jsonb_build_object(
+                'reactions', jsonb_build_array(jsonb_build_object('activity_id', $2::text))
+            ))
+        )
+        limit 1
+        "#,
+    )
+    .bind(board_id)
+    .bind(activity_id)
+}
+
+/// Locks the thread until the end of the transaction, see [update_posts_query].
+pub(super) fn build_by_id_for_update_query(thread_id: &Uuid) -> ThreadQuery<'_> {
+    sqlx::query_as::<_, ThreadSchema>(
+        r#"
+        select * from thread
+        where thread_id = $1
+        for update
+        "#,
+    )
+    .bind(thread_id)
+}
+
+/// Held until the end of the transaction, see [build_by_media_hash_query].
+pub(super) fn lock_media_hash_query<'q>(
+    board_id: &'q Uuid,
+    hash: &'q str,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(
+        r#"
+        select pg_advisory_xact_lock(hashtextextended($1::text || '/' || $2, 0))
+        "#,
+    )
+    .bind(board_id)
+    .bind(hash)
+}
+
+pub(super) fn build_by_media_hash_query<'q>(board_id: &'q Uuid, hash: &'q str) -> ThreadQuery<'q> {
+    sqlx::query_as::<_, ThreadSchema>(
+        r#"
+        select * from thread
+        where board_id = $1
+        and posts @> jsonb_build_object(
+            'posts', jsonb_build_array(jsonb_build_object(
+                'attachments', jsonb_build_array(jsonb_build_object('hash', $2::text))
+            ))
+        )
+        limit 1
+        "#,
+    )
+    .bind(board_id)
+    .bind(hash)
+}
+
+pub(super) fn build_by_remote_object_query<'q>(
+    board_id: &'q Uuid,
+    object_id: &'q str,
+) -> ThreadQuery<'q> {
+    sqlx::query_as::<_, ThreadSchema>(
+        r#"
+        select * from thread
+        where board_id = $1
+        and posts @> jsonb_build_object(
+            'posts', jsonb_build_array(jsonb_build_object(
+                'remote', jsonb_build_object('object_id', $2::text)
+            ))
+        )
+        limit 1
+        "#,
+    )
+    .bind(board_id)
+    .bind(object_id)
+}
+
 pub(super) fn update_posts_query<'q>(
     posts: &'q Json<PostsSchema>,
     thread_id: &'q Uuid,
@@ -58,3 +181,20 @@ pub(super) fn update_posts_query<'q>(
     .bind(posts)
     .bind(thread_id)
 }
+
+/// Appends in place, a post added meanwhile by another request is kept.
+pub(super) fn append_post_query<'q>(
+    post: &'q Json<PostSchema>,
+    thread_id: &'q Uuid,
+) -> ThreadQuery<'q> {
+    sqlx::query_as::<_, ThreadSchema>(
+        r#"
+        update thread
+        set posts = jsonb_set(posts, '{posts}', (posts -> 'posts') || jsonb_build_array($1::jsonb))
+        where thread_id = $2
+        returning *
+        "#,
+    )
+    .bind(post)
+    .bind(thread_id)
+}
@@ -1,19 +1,32 @@
 use crate::infra::AppState;
 use axum::{Router, routing::get};
 
+mod activitypub_routes;
+mod admin_routes;
+mod api_error;
+mod board_inbox;
 mod board_routes;
 mod file_routes;
 mod post_routes;
+mod post_submission;
+mod signed_request;
+mod static_routes;
 mod thread_routes;
+mod well_known_routes;
 
 pub(super) fn build_routes(app_state: AppState) -> Router {
     let api_routes = Router::new()
         .route("/", get(hello_handler))
-        .nest("/boards", board_routes::routes(app_state))
-        .nest("/files", file_routes::routes());
+        .nest("/admin", admin_routes::routes(app_state.clone()))
+        .nest("/boards", board_routes::routes(app_state.clone()))
+        .nest("/files", file_routes::routes(app_state.clone()));
     Router::new()
         .route("/", get(async || "Hello from the fediboard".to_string()))
         .nest("/api", api_routes)
+        .nest("/boards", activitypub_routes::routes(app_state.clone()))
+        .nest("/.well-known", well_known_routes::routes(app_state.clone()))
+        .nest("/nodeinfo", well_known_routes::nodeinfo_routes(app_state))
+        .nest("/static", static_routes::routes())
 }
 
 async fn hello_handler() -> String {
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    board::{Board, BoardUseCase},
+    federation::FederationUseCase,
+    infra::{
+        AppState,
+        activitypub::objects::{
+            self, ACTIVITY_STREAMS, OrderedCollection, OrderedCollectionPage, WithContext,
+        },
+        routing::{board_inbox, board_routes},
+    },
+    thread::{Post, Thread, ThreadError, ThreadUseCase},
+};
+
+pub(super) const ACTIVITY_JSON: &str = "application/activity+json";
+
+const OUTBOX_PAGE_SIZE: i64 = 20;
+
+/// Serializes with the ActivityPub content type instead of plain json.
+pub(super) struct ActivityJson<T>(pub(super) T);
+
+impl<T: Serialize> IntoResponse for ActivityJson<T> {
+    fn into_response(self) -> Response {
+        ([(header::CONTENT_TYPE, ACTIVITY_JSON)], Json(self.0)).into_response()
+    }
+}
+
+pub(super) fn routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/{board_name}", get(get_board_actor))
+        .route("/{board_name}/inbox", post(board_inbox::post_to_inbox))
+        .route("/{board_name}/poster", get(get_poster_actor))
+        .route("/{board_name}/poster/inbox", post(post_to_poster_inbox))
+        .route("/{board_name}/outbox", get(get_outbox))
+        .route("/{board_name}/threads/{thread_id}", get(get_thread_object))
+        .route(
+            "/{board_name}/threads/{thread_id}/posts/{post_id}",
+            get(get_post_object),
+        )
+        .route("/{board_name}/followers", get(get_followers))
+        .with_state(app_state)
+}
+
+/// ActivityPub clients ask for `application/activity+json` or the `ld+json` profile.
+pub(super) fn accepts_activity_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains(ACTIVITY_JSON) || value.contains("application/ld+json"))
+}
+
+async fn get_board_actor(
+    State(app_state): State<AppState>,
+    Path(board_name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !accepts_activity_json(&headers) {
+        return Ok(Redirect::to(&format!("/api/boards/{}", board_name)).into_response());
+    }
+    let board_use_case = app_state.di.board_use_case();
+    let board = board_use_case
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let keys = board_use_case
+        .get_board_keys(&board)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let group = objects::to_group(&app_state.federation, &board, &keys);
+    Ok(ActivityJson(group).into_response())
+}
+
+async fn get_poster_actor(
+    State(app_state): State<AppState>,
+    Path(board_name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !accepts_activity_json(&headers) {
+        return Ok(Redirect::to(&format!("/api/boards/{}", board_name)).into_response());
+    }
+    let board_use_case = app_state.di.board_use_case();
+    let board = board_use_case
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let keys = board_use_case
+        .get_board_keys(&board)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let poster = objects::to_poster(&app_state.federation, &board, &keys);
+    Ok(ActivityJson(poster).into_response())
+}
+
+/// Groups may notify the poster of replies and mentions, the board gets those through its own inbox.
+async fn post_to_poster_inbox() -> StatusCode {
+    StatusCode::ACCEPTED
+}
+
+/// The thread's opening post as a `Page`, browsers are sent to the thread in the api.
+async fn get_thread_object(
+    State(app_state): State<AppState>,
+    Path((board_name, thread_id)): Path<(String, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !accepts_activity_json(&headers) {
+        return Ok(redirect_to_thread(&board_name, &thread_id));
+    }
+    let (board, thread) = find_thread(&app_state, &board_name, &thread_id).await?;
+    let opening_post = thread.posts.posts.first().ok_or(StatusCode::NOT_FOUND)?;
+    // threads of mirrored boards are the group's posts, not ours
+    if let Some(remote) = &opening_post.remote {
+        return Ok(Redirect::to(&remote.object_id).into_response());
+    }
+    Ok(to_object_response(
+        &app_state,
+        &board,
+        &thread,
+        opening_post,
+    ))
+}
+
+/// A reply as a `Note`, posts that have a canonical id elsewhere redirect to it.
+async fn get_post_object(
+    State(app_state): State<AppState>,
+    Path((board_name, thread_id, post_id)): Path<(String, Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !accepts_activity_json(&headers) {
+        return Ok(redirect_to_thread(&board_name, &thread_id));
+    }
+    let (board, thread) = find_thread(&app_state, &board_name, &thread_id).await?;
+    let position = thread
+        .posts
+        .posts
+        .iter()
+        .position(|post| post.id == post_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let post = &thread.posts.posts[position];
+    if let Some(remote) = &post.remote {
+        return Ok(Redirect::to(&remote.object_id).into_response());
+    }
+    if position == 0 {
+        let thread_object_id = app_state
+            .federation
+            .thread_object_id(&board.name, &thread.thread_id);
+        return Ok(Redirect::permanent(&thread_object_id).into_response());
+    }
+    Ok(to_object_response(&app_state, &board, &thread, post))
+}
+
+async fn find_thread(
+    app_state: &AppState,
+    board_name: &str,
+    thread_id: &Uuid,
+) -> Result<(Board, Thread), StatusCode> {
+    let board = app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let thread = app_state
+        .di
+        .thread_use_case()
+        .get_thread_by_id(&thread_id.to_string(), &board.name)
+        .await
+        .map_err(|err| match err {
+            ThreadError::NotFound | ThreadError::IdError => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+    if thread.board_id != board.board_id {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok((board, thread))
+}
+
+fn to_object_response(
+    app_state: &AppState,
+    board: &Board,
+    thread: &Thread,
+    post: &Post,
+) -> Response {
+    let object = objects::to_post_object(&app_state.federation, board, thread, post);
+    ActivityJson(WithContext {
+        context: ACTIVITY_STREAMS.to_owned(),
+        object,
+    })
+    .into_response()
+}
+
+fn redirect_to_thread(board_name: &str, thread_id: &Uuid) -> Response {
+    Redirect::to(&format!("/api/boards/{}/threads/{}", board_name, thread_id)).into_response()
+}
+
+/// Without a `page` only the size and the first page are given, pages hold the newest posts first.
+async fn get_outbox(
+    State(app_state): State<AppState>,
+    Path(board_name): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    let board = app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let thread_use_case = app_state.di.thread_use_case();
+    let total_items = thread_use_case
+        .count_local_posts(&board)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let config = &app_state.federation;
+    let outbox_id = config.board_outbox(&board.name);
+    let page_id = |page: i64| format!("{}?page={}", outbox_id, page);
+    let page = match params.get("page") {
+        Some(page) => page
+            .parse::<i64>()
+            .ok()
+            .filter(|page| *page > 0)
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        None => {
+            return Ok(ActivityJson(OrderedCollection {
+                context: ACTIVITY_STREAMS.to_owned(),
+                id: outbox_id.clone(),
+                kind: "OrderedCollection".to_owned(),
+                total_items,
+                first: Some(page_id(1)),
+            })
+            .into_response());
+        }
+    };
+    let start = (page - 1).saturating_mul(OUTBOX_PAGE_SIZE);
+    let posts = thread_use_case
+        .get_local_posts(&board, OUTBOX_PAGE_SIZE, start)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ordered_items = posts
+        .iter()
+        .map(|(thread, post)| {
+            let object = objects::to_post_object(config, &board, thread, post);
+            objects::create_activity(config, &board.name, object)
+        })
+        .collect();
+    Ok(ActivityJson(OrderedCollectionPage {
+        context: ACTIVITY_STREAMS.to_owned(),
+        id: page_id(page),
+        kind: "OrderedCollectionPage".to_owned(),
+        part_of: outbox_id.clone(),
+        prev: (page > 1).then(|| page_id(page - 1)),
+        next: (start.saturating_add(OUTBOX_PAGE_SIZE) < total_items).then(|| page_id(page + 1)),
+        ordered_items,
+    })
+    .into_response())
+}
+
+async fn get_followers(
+    State(app_state): State<AppState>,
+    Path(board_name): Path<String>,
+) -> Result<ActivityJson<OrderedCollection>, StatusCode> {
+    let board = app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let federation_use_case = app_state.di.federation_use_case();
+    // only the count is public, like on most servers
+    let total_items = match federation_use_case.count_followers(&board).await {
+        Ok(count) => count,
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    Ok(ActivityJson(OrderedCollection {
+        context: ACTIVITY_STREAMS.to_owned(),
+        id: app_state.federation.board_followers(&board.name),
+        kind: "OrderedCollection".to_owned(),
+        total_items,
+        first: None,
+    }))
+}
@@ -0,0 +1,490 @@
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get, post},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    board::{Board, BoardError, BoardSettings, BoardSettingsUpdate, BoardUseCase},
+    federation::{DeadInbox, DeliveryUseCase},
+    infra::{
+        AppState, DepenencyInjector,
+        activitypub::{board_signer, objects},
+        routing::{api_error::ApiError, board_routes},
+    },
+    moderation::{
+        BannedHash, DomainPolicy, HashImport, ModerationError, ModerationUseCase, Policy,
+    },
+};
+
+/// Hash lists can be long, a sha256sum line is about 65 bytes.
+const MAX_HASH_LIST_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct BannedHashView {
+    pub(super) hash: String,
+    pub(super) reason: String,
+    pub(super) created_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct BanCreation {
+    pub(super) hash: String,
+    pub(super) reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct ImportParams {
+    pub(super) reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct HashImportView {
+    pub(super) imported: u64,
+    pub(super) invalid: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct DomainPolicyView {
+    pub(super) domain: String,
+    pub(super) policy: String,
+    pub(super) reason: String,
+    pub(super) created_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct DomainPolicyCreation {
+    pub(super) domain: String,
+    pub(super) policy: String,
+    pub(super) reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct DeadInboxView {
+    pub(super) inbox: String,
+    pub(super) dead_since: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct MirrorView {
+    pub(super) board_name: String,
+    pub(super) actor_id: String,
+    /// Whether the group accepted the board's follow, posts only arrive once it did.
+    pub(super) accepted: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct BoardSettingsView {
+    pub(super) max_file_size: usize,
+    pub(super) max_files_per_post: usize,
+    pub(super) allowed_mime_types: Vec<String>,
+    pub(super) keep_metadata: bool,
+    pub(super) reject_duplicate_files: bool,
+    pub(super) spoilers_enabled: bool,
+}
+
+/// Settings left out of the body keep their current value.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct BoardSettingsPatch {
+    pub(super) max_file_size: Option<usize>,
+    pub(super) max_files_per_post: Option<usize>,
+    pub(super) allowed_mime_types: Option<Vec<String>>,
+    pub(super) keep_metadata: Option<bool>,
+    pub(super) reject_duplicate_files: Option<bool>,
+    pub(super) spoilers_enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct MirrorCreation {
+    pub(super) board_name: String,
+    pub(super) actor_id: String,
+}
+
+pub(super) fn routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/banned-hashes", get(get_banned_hashes).post(ban_hash))
+        .route(
+            "/banned-hashes/import",
+            post(import_banned_hashes).layer(DefaultBodyLimit::max(MAX_HASH_LIST_SIZE)),
+        )
+        .route("/banned-hashes/{hash}", delete(unban_hash))
+        .route(
+            "/domain-policies",
+            get(get_domain_policies).post(set_domain_policy),
+        )
+        .route("/domain-policies/{domain}", delete(remove_domain_policy))
+        .route("/dead-inboxes", get(get_dead_inboxes))
+        .route("/dead-inboxes/{host}", delete(revive_host))
+        .route("/mirrors", get(get_mirrors).post(create_mirror))
+        .route(
+            "/boards/{board_name}/settings",
+            get(get_board_settings).patch(update_board_settings),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_admin,
+        ))
+        .with_state(app_state)
+}
+
+/// Admin routes need `Authorization: Bearer <ADMIN_TOKEN>` and are off without a token.
+async fn require_admin(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let expected = match &app_state.admin_token {
+        Some(token) => token,
+        None => {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "admin api is disabled",
+            ));
+        }
+    };
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(token) if tokens_match(token, expected) => Ok(next.run(request).await),
+        _ => Err(StatusCode::UNAUTHORIZED.into()),
+    }
+}
+
+/// Compares digests of the tokens without stopping at the first difference, so neither the
+/// length nor a prefix of the admin token can be guessed from response times.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let provided = Sha256::digest(provided.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    provided
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+async fn get_banned_hashes(
+    State(di): State<DepenencyInjector>,
+) -> Result<Json<Vec<BannedHashView>>, ApiError> {
+    match di.moderation_use_case().get_banned_hashes().await {
+        Ok(hashes) => Ok(Json(hashes.into_iter().map(to_banned_hash_view).collect())),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+/// A hash that is banned already answers with its existing ban and `200 OK`.
+async fn ban_hash(
+    State(di): State<DepenencyInjector>,
+    Json(ban_creation): Json<BanCreation>,
+) -> Result<(StatusCode, Json<BannedHashView>), ApiError> {
+    let use_case = di.moderation_use_case();
+    match use_case
+        .ban_hash(&ban_creation.hash, &ban_creation.reason)
+        .await
+    {
+        Ok((banned, true)) => Ok((StatusCode::CREATED, Json(to_banned_hash_view(banned)))),
+        Ok((banned, false)) => Ok((StatusCode::OK, Json(to_banned_hash_view(banned)))),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn unban_hash(
+    State(di): State<DepenencyInjector>,
+    Path(hash): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match di.moderation_use_case().unban_hash(&hash).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn import_banned_hashes(
+    State(di): State<DepenencyInjector>,
+    Query(params): Query<ImportParams>,
+    hash_list: String,
+) -> Result<Json<HashImportView>, ApiError> {
+    let reason = params.reason.unwrap_or("imported".to_owned());
+    let use_case = di.moderation_use_case();
+    match use_case.import_banned_hashes(&hash_list, &reason).await {
+        Ok(import) => Ok(Json(to_import_view(import))),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn get_domain_policies(
+    State(di): State<DepenencyInjector>,
+) -> Result<Json<Vec<DomainPolicyView>>, ApiError> {
+    match di.moderation_use_case().get_domain_policies().await {
+        Ok(policies) => Ok(Json(
+            policies.into_iter().map(to_domain_policy_view).collect(),
+        )),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn set_domain_policy(
+    State(di): State<DepenencyInjector>,
+    Json(creation): Json<DomainPolicyCreation>,
+) -> Result<Json<DomainPolicyView>, ApiError> {
+    let Ok(policy) = creation.policy.parse::<Policy>() else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "expected block, reject_media or accept",
+        ));
+    };
+    let use_case = di.moderation_use_case();
+    match use_case
+        .set_domain_policy(&creation.domain, policy, &creation.reason)
+        .await
+    {
+        Ok(domain_policy) => Ok(Json(to_domain_policy_view(domain_policy))),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn remove_domain_policy(
+    State(di): State<DepenencyInjector>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match di.moderation_use_case().remove_domain_policy(&domain).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+async fn get_dead_inboxes(
+    State(di): State<DepenencyInjector>,
+) -> Result<Json<Vec<DeadInboxView>>, ApiError> {
+    match di.delivery_use_case().get_dead_inboxes().await {
+        Ok(dead_inboxes) => Ok(Json(
+            dead_inboxes.into_iter().map(to_dead_inbox_view).collect(),
+        )),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+async fn revive_host(
+    State(di): State<DepenencyInjector>,
+    Path(host): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match di.delivery_use_case().revive_host(&host).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+async fn get_board_settings(
+    State(di): State<DepenencyInjector>,
+    Path(board_name): Path<String>,
+) -> Result<Json<BoardSettingsView>, ApiError> {
+    match di.board_use_case().get_board_by_name(&board_name).await {
+        Ok(board) => Ok(Json(to_board_settings_view(board.settings))),
+        Err(err) => Err(board_routes::to_status_code(err).into()),
+    }
+}
+
+async fn update_board_settings(
+    State(di): State<DepenencyInjector>,
+    Path(board_name): Path<String>,
+    Json(patch): Json<BoardSettingsPatch>,
+) -> Result<Json<BoardSettingsView>, ApiError> {
+    let board_use_case = di.board_use_case();
+    let board = board_use_case
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    match board_use_case
+        .update_board_settings(&board, to_settings_update(patch))
+        .await
+    {
+        Ok(board) => Ok(Json(to_board_settings_view(board.settings))),
+        Err(BoardError::InvalidSettings(message)) => {
+            Err(ApiError::new(StatusCode::BAD_REQUEST, message))
+        }
+        Err(err) => Err(board_routes::to_status_code(err).into()),
+    }
+}
+
+async fn get_mirrors(
+    State(di): State<DepenencyInjector>,
+) -> Result<Json<Vec<MirrorView>>, ApiError> {
+    match di.board_use_case().get_all_boards().await {
+        Ok(boards) => Ok(Json(
+            boards.into_iter().filter_map(to_mirror_view).collect(),
+        )),
+        Err(err) => Err(board_routes::to_status_code(err).into()),
+    }
+}
+
+/// Creates the board and follows the group, the board is dropped again if the group is unreachable.
+async fn create_mirror(
+    State(app_state): State<AppState>,
+    Json(creation): Json<MirrorCreation>,
+) -> Result<(StatusCode, Json<MirrorView>), ApiError> {
+    refuse_blocked(&app_state.di, &creation.actor_id).await?;
+    let board_use_case = app_state.di.board_use_case();
+    let board = match board_use_case
+        .create_mirror_board(&creation.board_name, &creation.actor_id)
+        .await
+    {
+        Ok(board) => board,
+        Err(BoardError::InvalidName) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "board names are up to 32 lowercase letters, digits and underscores",
+            ));
+        }
+        Err(BoardError::AlreadyExists) => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "the board exists or the group is mirrored already",
+            ));
+        }
+        Err(err) => return Err(board_routes::to_status_code(err).into()),
+    };
+    if let Err(err) = follow_group(&app_state, &board).await {
+        if board_use_case.delete_board(board).await.is_err() {
+            eprintln!("Could not remove the board of {}", creation.actor_id);
+        }
+        return Err(err);
+    }
+    let view = to_mirror_view(board).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+async fn follow_group(app_state: &AppState, board: &Board) -> Result<(), ApiError> {
+    let actor_id = board
+        .mirror
+        .as_ref()
+        .map(|mirror| mirror.actor_id.as_str())
+        .unwrap_or_default();
+    let signer = board_signer(app_state, &board.name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let group = app_state
+        .remote_actors
+        .get_actor(actor_id, &signer)
+        .await
+        .ok_or(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "the group could not be fetched",
+        ))?;
+    // the inbox may live on another host than the group
+    refuse_blocked(&app_state.di, &group.inbox).await?;
+    let mut follow = objects::board_activity(
+        &app_state.federation,
+        &board.name,
+        "Follow",
+        Value::String(group.actor_id.clone()),
+    );
+    follow.to = vec![group.actor_id];
+    let follow = serde_json::to_string(&follow).unwrap_or_default();
+    app_state
+        .di
+        .delivery_use_case()
+        .enqueue(board, vec![group.inbox], &follow)
+        .await
+        .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Deliveries to blocked domains are dropped, so the group would never see the `Follow`.
+async fn refuse_blocked(di: &DepenencyInjector, url: &str) -> Result<(), ApiError> {
+    let host = objects::host_of(url).ok_or(ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "expected the group's actor id as an absolute url",
+    ))?;
+    match di.moderation_use_case().get_policy_for_host(&host).await {
+        Ok(Policy::Block) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "the group's domain is blocked",
+        )),
+        Ok(_) => Ok(()),
+        Err(err) => Err(to_api_error(err)),
+    }
+}
+
+fn to_banned_hash_view(banned: BannedHash) -> BannedHashView {
+    BannedHashView {
+        hash: banned.hash,
+        reason: banned.reason,
+        created_at: banned.created_at.to_rfc3339(),
+    }
+}
+
+fn to_domain_policy_view(domain_policy: DomainPolicy) -> DomainPolicyView {
+    DomainPolicyView {
+        domain: domain_policy.domain,
+        policy: domain_policy.policy.as_str().to_owned(),
+        reason: domain_policy.reason,
+        created_at: domain_policy.created_at.to_rfc3339(),
+    }
+}
+
+fn to_dead_inbox_view(dead_inbox: DeadInbox) -> DeadInboxView {
+    DeadInboxView {
+        inbox: dead_inbox.inbox,
+        dead_since: dead_inbox.dead_since.to_rfc3339(),
+    }
+}
+
+fn to_board_settings_view(settings: BoardSettings) -> BoardSettingsView {
+    BoardSettingsView {
+        max_file_size: settings.max_file_size,
+        max_files_per_post: settings.max_files_per_post,
+        allowed_mime_types: settings.allowed_mime_types,
+        keep_metadata: settings.keep_metadata,
+        reject_duplicate_files: settings.reject_duplicate_files,
+        spoilers_enabled: settings.spoilers_enabled,
+    }
+}
+
+fn to_settings_update(patch: BoardSettingsPatch) -> BoardSettingsUpdate {
+    BoardSettingsUpdate {
+        max_file_size: patch.max_file_size,
+        max_files_per_post: patch.max_files_per_post,
+        allowed_mime_types: patch.allowed_mime_types,
+        keep_metadata: patch.keep_metadata,
+        reject_duplicate_files: patch.reject_duplicate_files,
+        spoilers_enabled: patch.spoilers_enabled,
+    }
+}
+
+fn to_mirror_view(board: Board) -> Option<MirrorView> {
+    let mirror = board.mirror?;
+    Some(MirrorView {
+        board_name: board.name,
+        actor_id: mirror.actor_id,
+        accepted: mirror.accepted,
+    })
+}
+
+fn to_import_view(import: HashImport) -> HashImportView {
+    HashImportView {
+        imported: import.imported,
+        invalid: import.invalid,
+    }
+}
+
+fn to_api_error(err: ModerationError) -> ApiError {
+    match err {
+        ModerationError::NotFound => StatusCode::NOT_FOUND.into(),
+        ModerationError::DbError => StatusCode::INTERNAL_SERVER_ERROR.into(),
+        ModerationError::InvalidHash => ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "expected a hex encoded sha-256 hash",
+        ),
+        ModerationError::InvalidDomain => ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "expected a domain like example.com or *.example.com",
+        ),
+    }
+}
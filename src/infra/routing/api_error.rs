@@ -0,0 +1,53 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct ErrorView {
+    pub(super) error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) thread_id: Option<String>,
+}
+
+/// An error status with a message for the client, rendered as a JSON body.
+pub(super) struct ApiError {
+    status: StatusCode,
+    message: String,
+    thread_id: Option<Uuid>,
+}
+
+impl ApiError {
+    pub(super) fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            thread_id: None,
+        }
+    }
+
+    pub(super) fn with_thread_id(mut self, thread_id: Uuid) -> Self {
+        self.thread_id = Some(thread_id);
+        self
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let message = status.canonical_reason().unwrap_or_default();
+        Self::new(status, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorView {
+            error: self.message,
+            thread_id: self.thread_id.map(|id| id.to_string()),
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
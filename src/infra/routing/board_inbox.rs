@@ -0,0 +1,162 @@
+mod mirror;
+mod remote_posts;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde_json::Value;
+
+use crate::{
+    board::{Board, BoardUseCase},
+    federation::{DeliveryUseCase, FederationUseCase, Follower},
+    infra::{
+        AppState,
+        activitypub::{
+            board_signer,
+            objects::{self, board_activity},
+        },
+        routing::{api_error::ApiError, board_routes, signed_request::SignedActivity},
+    },
+    thread::ReactionKind,
+};
+
+/// Signatures are checked before any activity is looked at, unknown activities are ignored.
+pub(super) async fn post_to_inbox(
+    State(app_state): State<AppState>,
+    Path(board_name): Path<String>,
+    signed: SignedActivity,
+) -> Result<StatusCode, ApiError> {
+    let board = app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(&board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let activity = signed.activity;
+    let from_mirrored_group = mirror::is_from_mirrored_group(&board, &activity);
+    match objects::kind_of(&activity) {
+        Some("Accept") if from_mirrored_group => {
+            mirror::accept(&app_state, &board, &activity).await
+        }
+        Some("Create") if from_mirrored_group => {
+            mirror::ingest_create(&app_state, &board, &activity, signed.policy).await
+        }
+        Some("Announce") if from_mirrored_group => {
+            mirror::ingest_announce(&app_state, &board, &activity, signed.policy).await
+        }
+        Some("Follow") => accept_follow(&app_state, &board, activity).await,
+        Some("Undo") => undo(&app_state, &board, &activity).await,
+        Some("Create") => {
+            remote_posts::accept_reply(&app_state, &board, &activity, signed.policy).await
+        }
+        Some("Update") => remote_posts::update(&app_state, &board, &activity, signed.policy).await,
+        Some("Delete") => remote_posts::delete(&app_state, &board, &activity).await,
+        Some("Like") => {
+            remote_posts::react(&app_state, &board, &activity, ReactionKind::Like).await
+        }
+        Some("Announce") => {
+            remote_posts::react(&app_state, &board, &activity, ReactionKind::Announce).await
+        }
+        Some(_) => Ok(StatusCode::ACCEPTED),
+        None => Err(bad_activity("the activity has no type")),
+    }
+}
+
+async fn accept_follow(
+    app_state: &AppState,
+    board: &Board,
+    follow: Value,
+) -> Result<StatusCode, ApiError> {
+    let actor_id = follow
+        .get("actor")
+        .and_then(objects::id_of)
+        .unwrap_or_default();
+    let board_actor_id = app_state.federation.board_actor_id(&board.name);
+    if follow.get("object").and_then(objects::id_of) != Some(board_actor_id.as_str()) {
+        return Err(bad_activity("only the board itself can be followed"));
+    }
+    let signer = board_signer(app_state, &board.name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let actor = app_state
+        .remote_actors
+        .get_actor(actor_id, &signer)
+        .await
+        .ok_or(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "the follower could not be fetched",
+        ))?;
+    let follower = Follower {
+        actor_id: actor.actor_id,
+        inbox: actor.inbox,
+        shared_inbox: actor.shared_inbox,
+        follow_id: objects::id_of(&follow).map(str::to_owned),
+    };
+    if app_state
+        .di
+        .federation_use_case()
+        .follow(board, &follower)
+        .await
+        .is_err()
+    {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    let delivery_use_case = app_state.di.delivery_use_case();
+    let mut accept = board_activity(&app_state.federation, &board.name, "Accept", follow);
+    accept.to = vec![follower.actor_id.clone()];
+    let accept = serde_json::to_string(&accept).unwrap_or_default();
+    if delivery_use_case
+        .enqueue(board, vec![follower.inbox], &accept)
+        .await
+        .is_err()
+    {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn undo(app_state: &AppState, board: &Board, undo: &Value) -> Result<StatusCode, ApiError> {
+    let actor_id = undo
+        .get("actor")
+        .and_then(objects::id_of)
+        .unwrap_or_default();
+    let object = undo.get("object").unwrap_or(&Value::Null);
+    // the signer can only undo its own activities
+    match objects::kind_of(object) {
+        Some("Like" | "Announce") => {
+            remote_posts::unreact(app_state, board, actor_id, object).await
+        }
+        Some("Follow") => unfollow(app_state, board, actor_id, None).await,
+        Some(_) => Ok(StatusCode::ACCEPTED),
+        None => {
+            // referenced by its id alone, it may have been a like, a boost or a follow
+            let undone_id = objects::id_of(object).ok_or(bad_activity("the undo has no object"))?;
+            if remote_posts::unreact_by_id(app_state, board, actor_id, undone_id).await? {
+                return Ok(StatusCode::ACCEPTED);
+            }
+            unfollow(app_state, board, actor_id, Some(undone_id)).await
+        }
+    }
+}
+
+async fn unfollow(
+    app_state: &AppState,
+    board: &Board,
+    actor_id: &str,
+    follow_id: Option<&str>,
+) -> Result<StatusCode, ApiError> {
+    match app_state
+        .di
+        .federation_use_case()
+        .unfollow(board, actor_id, follow_id)
+        .await
+    {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+pub(super) fn bad_activity(message: &str) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, message)
+}
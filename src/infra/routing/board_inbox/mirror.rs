@@ -0,0 +1,229 @@
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::{
+    board::{Board, BoardUseCase},
+    infra::{
+        AppState,
+        activitypub::{board_signer, objects},
+        routing::{api_error::ApiError, board_routes, post_routes, thread_routes},
+    },
+    moderation::{ModerationUseCase, Policy},
+    thread::{PostUseCase, ReactionKind, ThreadCreation, ThreadError, ThreadUseCase},
+};
+
+use super::{
+    bad_activity,
+    remote_posts::{self, actor_of, check_authored, find_post, find_remote_thread, to_remote_post},
+    undo,
+};
+
+pub(super) fn is_from_mirrored_group(board: &Board, activity: &Value) -> bool {
+    board
+        .mirror
+        .as_ref()
+        .is_some_and(|mirror| mirror.actor_id == actor_of(activity))
+}
+
+/// The group took the board as a follower, its posts arrive from now on.
+pub(super) async fn accept(
+    app_state: &AppState,
+    board: &Board,
+    accept: &Value,
+) -> Result<StatusCode, ApiError> {
+    let board_actor_id = app_state.federation.board_actor_id(&board.name);
+    let is_board_follow = match accept.get("object") {
+        Some(Value::String(follow_id)) => follow_id.starts_with(&format!("{}/", board_actor_id)),
+        Some(follow) => {
+            objects::kind_of(follow) == Some("Follow")
+                && follow.get("actor").and_then(objects::id_of) == Some(board_actor_id.as_str())
+        }
+        None => false,
+    };
+    if !is_board_follow {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    match app_state
+        .di
+        .board_use_case()
+        .accept_mirror_follow(board)
+        .await
+    {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+/// A post of the group itself, or of a member whose `Create` the group announced.
+pub(super) async fn ingest_create(
+    app_state: &AppState,
+    board: &Board,
+    create: &Value,
+    policy: Policy,
+) -> Result<StatusCode, ApiError> {
+    let actor_id = actor_of(create);
+    let vouching_host = objects::host_of(actor_id);
+    let Some(post) = resolve(app_state, board, create.get("object"), vouching_host).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    ingest_post(app_state, board, &post, actor_id, policy).await
+}
+
+/// Lemmy groups announce the activities of their members, other servers the posts themselves.
+pub(super) async fn ingest_announce(
+    app_state: &AppState,
+    board: &Board,
+    announce: &Value,
+    policy: Policy,
+) -> Result<StatusCode, ApiError> {
+    let group_host = objects::host_of(actor_of(announce));
+    let Some(object) = resolve(app_state, board, announce.get("object"), group_host).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let kind = objects::kind_of(&object);
+    let mut policy = policy;
+    if matches!(
+        kind,
+        Some("Create" | "Update" | "Delete" | "Like" | "Dislike" | "Undo")
+    ) {
+        // the activity was taken from where its id points, its actor has to live there
+        if objects::id_of(&object).and_then(objects::host_of) != objects::host_of(actor_of(&object))
+        {
+            return Err(bad_activity(
+                "the announced activity is not from its actor's server",
+            ));
+        }
+        policy = match author_policy(app_state, actor_of(&object), policy).await? {
+            Some(policy) => policy,
+            None => return Ok(StatusCode::ACCEPTED),
+        };
+    }
+    // from here on an announced activity is handled as if its actor had signed it
+    match kind {
+        Some("Create") => ingest_create(app_state, board, &object, policy).await,
+        Some("Update") => remote_posts::update(app_state, board, &object, policy).await,
+        Some("Delete") => remote_posts::delete(app_state, board, &object).await,
+        Some("Like") => remote_posts::react(app_state, board, &object, ReactionKind::Like).await,
+        Some("Undo") => undo(app_state, board, &object).await,
+        // downvotes are not counted
+        Some("Dislike") => Ok(StatusCode::ACCEPTED),
+        Some(_) => {
+            let author = object
+                .get("attributedTo")
+                .and_then(objects::id_of)
+                .unwrap_or_default();
+            ingest_post(app_state, board, &object, author, policy).await
+        }
+        None => Err(bad_activity("the announced object has no type")),
+    }
+}
+
+/// Takes an embedded object if the server that sent it vouches for its id, fetches it otherwise.
+/// Our own posts come back announced by the group, they are left out.
+async fn resolve(
+    app_state: &AppState,
+    board: &Board,
+    object: Option<&Value>,
+    vouching_host: Option<String>,
+) -> Result<Option<Value>, ApiError> {
+    let object = object.ok_or(bad_activity("the activity has no object"))?;
+    let object_id = objects::id_of(object).ok_or(bad_activity("the object has no id"))?;
+    if object_id.starts_with(&format!("{}/", app_state.federation.public_url)) {
+        return Ok(None);
+    }
+    if object.is_object() && objects::host_of(object_id) == vouching_host {
+        return Ok(Some(object.clone()));
+    }
+    let signer = board_signer(app_state, &board.name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    match app_state.activitypub.fetch(object_id, &signer).await {
+        Ok(fetched) if objects::id_of(&fetched) == Some(object_id) => Ok(Some(fetched)),
+        _ => Err(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "the object could not be fetched",
+        )),
+    }
+}
+
+/// Posts without `inReplyTo` open a thread, replies go into the thread they answer.
+async fn ingest_post(
+    app_state: &AppState,
+    board: &Board,
+    object: &Value,
+    author: &str,
+    policy: Policy,
+) -> Result<StatusCode, ApiError> {
+    if !matches!(objects::kind_of(object), Some("Page" | "Note" | "Article")) {
+        return Ok(StatusCode::ACCEPTED); // votes, polls and the like are not mirrored
+    }
+    let object_id = check_authored(object, author)?;
+    // the group may send a post and announce it as well
+    if find_remote_thread(app_state, board, object_id)
+        .await?
+        .is_some()
+    {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    let Some(policy) = author_policy(app_state, author, policy).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let post = to_remote_post(author, object, policy)?;
+    let Some(in_reply_to) = object.get("inReplyTo").and_then(objects::id_of) else {
+        let thread_creation = ThreadCreation {
+            name: post.name,
+            subject: post.subject,
+            content: post.content,
+            media_url: post.media_url,
+            attachments: post.attachments,
+            spoiler: post.spoiler,
+            remote: post.remote,
+        };
+        return match app_state
+            .di
+            .thread_use_case()
+            .create_thread(board.clone(), thread_creation)
+            .await
+        {
+            Ok(_) => Ok(StatusCode::ACCEPTED),
+            Err(err) => Err(thread_routes::to_api_error(err)),
+        };
+    };
+    let thread = match find_post(app_state, board, in_reply_to).await {
+        Ok((thread, _)) => thread,
+        // a reply to a post from before the board followed the group
+        Err(ThreadError::NotFound | ThreadError::IdError) => return Ok(StatusCode::ACCEPTED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    };
+    match app_state
+        .di
+        .post_use_case()
+        .post_into_thread(board, thread, post)
+        .await
+    {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(err) => Err(post_routes::to_status_code(err).into()),
+    }
+}
+
+/// Members from blocked servers are left out of the mirror, `None` for them.
+async fn author_policy(
+    app_state: &AppState,
+    author: &str,
+    group_policy: Policy,
+) -> Result<Option<Policy>, ApiError> {
+    let author_policy = match objects::host_of(author) {
+        Some(host) => app_state
+            .di
+            .moderation_use_case()
+            .get_policy_for_host(&host)
+            .await
+            .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))?,
+        None => Policy::Accept,
+    };
+    Ok(match author_policy {
+        Policy::Block => None,
+        Policy::RejectMedia => Some(Policy::RejectMedia),
+        Policy::Accept => Some(group_policy),
+    })
+}
@@ -0,0 +1,331 @@
+use axum::http::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    board::Board,
+    infra::{
+        AppState,
+        activitypub::{board_signer, objects},
+        routing::{api_error::ApiError, board_routes, post_routes},
+    },
+    moderation::Policy,
+    thread::{
+        Post, PostError, PostUseCase, Reaction, ReactionKind, Thread, ThreadError, ThreadUseCase,
+    },
+};
+
+use super::bad_activity;
+
+pub(super) async fn accept_reply(
+    app_state: &AppState,
+    board: &Board,
+    create: &Value,
+    policy: Policy,
+) -> Result<StatusCode, ApiError> {
+    let actor_id = actor_of(create);
+    let note = match create.get("object") {
+        Some(Value::String(object_id)) => {
+            let signer = board_signer(app_state, &board.name)
+                .await
+                .map_err(board_routes::to_status_code)?;
+            match app_state.activitypub.fetch(object_id, &signer).await {
+                Ok(note) if objects::id_of(&note) == Some(object_id) => note,
+                _ => {
+                    return Err(ApiError::new(
+                        StatusCode::BAD_GATEWAY,
+                        "the note could not be fetched",
+                    ));
+                }
+            }
+        }
+        Some(note) => note.clone(),
+        None => return Err(bad_activity("the activity has no object")),
+    };
+    if objects::kind_of(&note) != Some("Note") {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    let note_id = check_authored(&note, actor_id)?;
+    let in_reply_to = note
+        .get("inReplyTo")
+        .and_then(objects::id_of)
+        .ok_or(unknown_reply_target())?;
+    let thread = match find_post(app_state, board, in_reply_to).await {
+        Ok((thread, _)) => thread,
+        Err(ThreadError::NotFound | ThreadError::IdError) => return Err(unknown_reply_target()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    };
+    // deliveries are retried, a note is only taken once
+    if thread.posts.posts.iter().any(|post| {
+        post.remote
+            .as_ref()
+            .is_some_and(|remote| remote.object_id == note_id)
+    }) {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    let mut post = to_remote_post(actor_id, &note, policy)?;
+    if let Some(remote) = post.remote.as_mut() {
+        remote.activity = Some(create.to_string());
+    }
+    match app_state
+        .di
+        .post_use_case()
+        .post_into_thread(board, thread, post)
+        .await
+    {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(err) => Err(post_routes::to_status_code(err).into()),
+    }
+}
+
+/// Edits of posts we never took, or of actors and other objects, are ignored.
+pub(super) async fn update(
+    app_state: &AppState,
+    board: &Board,
+    update: &Value,
+    policy: Policy,
+) -> Result<StatusCode, ApiError> {
+    let actor_id = actor_of(update);
+    let note = update.get("object").unwrap_or(&Value::Null);
+    // threads of mirrored boards may open with a `Page` or `Article`
+    if !matches!(objects::kind_of(note), Some("Note" | "Page" | "Article")) {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    let note_id = check_authored(note, actor_id)?;
+    let Some(thread) = find_remote_thread(app_state, board, note_id).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let updated = to_remote_post(actor_id, note, policy)?;
+    to_inbox_result(
+        app_state
+            .di
+            .post_use_case()
+            .update_remote_post(board, thread, updated)
+            .await,
+    )
+}
+
+/// Servers send deletes to everyone they know, most are about posts we never saw.
+pub(super) async fn delete(
+    app_state: &AppState,
+    board: &Board,
+    delete: &Value,
+) -> Result<StatusCode, ApiError> {
+    let actor_id = actor_of(delete);
+    // the object may be the deleted id or a `Tombstone` carrying it
+    let object_id = delete
+        .get("object")
+        .and_then(objects::id_of)
+        .ok_or(bad_activity("the activity has no object"))?;
+    let Some(thread) = find_remote_thread(app_state, board, object_id).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    to_inbox_result(
+        app_state
+            .di
+            .post_use_case()
+            .delete_remote_post(thread, object_id, actor_id)
+            .await,
+    )
+}
+
+pub(super) async fn react(
+    app_state: &AppState,
+    board: &Board,
+    activity: &Value,
+    kind: ReactionKind,
+) -> Result<StatusCode, ApiError> {
+    let activity_id = objects::id_of(activity).ok_or(bad_activity("the activity has no id"))?;
+    let object_id = activity
+        .get("object")
+        .and_then(objects::id_of)
+        .ok_or(bad_activity("the activity has no object"))?;
+    let (thread, post_id) = match find_post(app_state, board, object_id).await {
+        Ok(found) => found,
+        Err(ThreadError::NotFound | ThreadError::IdError) => return Ok(StatusCode::ACCEPTED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    };
+    let reaction = Reaction {
+        kind,
+        actor_id: actor_of(activity).to_owned(),
+        activity_id: activity_id.to_owned(),
+    };
+    to_inbox_result(
+        app_state
+            .di
+            .post_use_case()
+            .add_reaction(thread, post_id, reaction)
+            .await,
+    )
+}
+
+/// Undoes a `Like` or `Announce`, which has to be embedded to find the post it was about.
+pub(super) async fn unreact(
+    app_state: &AppState,
+    board: &Board,
+    actor_id: &str,
+    reaction: &Value,
+) -> Result<StatusCode, ApiError> {
+    let (Some(activity_id), Some(object_id)) = (
+        objects::id_of(reaction),
+        reaction.get("object").and_then(objects::id_of),
+    ) else {
+        return Err(bad_activity("the undone activity has no id or object"));
+    };
+    let (thread, _) = match find_post(app_state, board, object_id).await {
+        Ok(found) => found,
+        Err(ThreadError::NotFound | ThreadError::IdError) => return Ok(StatusCode::ACCEPTED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    };
+    to_inbox_result(
+        app_state
+            .di
+            .post_use_case()
+            .remove_reaction(thread, activity_id, actor_id)
+            .await,
+    )
+}
+
+/// Undoes a `Like` or `Announce` referenced by its id alone, false if no post has it.
+pub(super) async fn unreact_by_id(
+    app_state: &AppState,
+    board: &Board,
+    actor_id: &str,
+    activity_id: &str,
+) -> Result<bool, ApiError> {
+    let thread = match app_state
+        .di
+        .thread_use_case()
+        .get_thread_by_reaction(board, activity_id)
+        .await
+    {
+        Ok(thread) => thread,
+        Err(ThreadError::NotFound) => return Ok(false),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    };
+    to_inbox_result(
+        app_state
+            .di
+            .post_use_case()
+            .remove_reaction(thread, activity_id, actor_id)
+            .await,
+    )?;
+    Ok(true)
+}
+
+/// Finds a post by its ActivityPub id, a thread's id stands for its opening post.
+pub(super) async fn find_post(
+    app_state: &AppState,
+    board: &Board,
+    object_id: &str,
+) -> Result<(Thread, Uuid), ThreadError> {
+    let Some(local) = app_state.federation.parse_object_id(object_id) else {
+        // not one of ours, but maybe a federated reply in one of our threads
+        let thread = app_state
+            .di
+            .thread_use_case()
+            .get_thread_by_remote_object(board, object_id)
+            .await?;
+        let post_id = thread
+            .posts
+            .posts
+            .iter()
+            .find(|post| {
+                post.remote
+                    .as_ref()
+                    .is_some_and(|remote| remote.object_id == object_id)
+            })
+            .ok_or(ThreadError::NotFound)?
+            .id;
+        return Ok((thread, post_id));
+    };
+    if local.board_name != board.name {
+        return Err(ThreadError::NotFound);
+    }
+    let thread = app_state
+        .di
+        .thread_use_case()
+        .get_thread_by_id(&local.thread_id.to_string(), &board.name)
+        .await?;
+    if thread.board_id != board.board_id {
+        return Err(ThreadError::NotFound);
+    }
+    let post_id = match local.post_id {
+        Some(post_id) => post_id,
+        None => thread.posts.posts.first().ok_or(ThreadError::NotFound)?.id,
+    };
+    if !thread.posts.posts.iter().any(|post| post.id == post_id) {
+        return Err(ThreadError::NotFound);
+    }
+    Ok((thread, post_id))
+}
+
+pub(super) async fn find_remote_thread(
+    app_state: &AppState,
+    board: &Board,
+    object_id: &str,
+) -> Result<Option<Thread>, ApiError> {
+    match app_state
+        .di
+        .thread_use_case()
+        .get_thread_by_remote_object(board, object_id)
+        .await
+    {
+        Ok(thread) => Ok(Some(thread)),
+        Err(ThreadError::NotFound) => Ok(None),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+pub(super) fn to_remote_post(
+    actor_id: &str,
+    note: &Value,
+    policy: Policy,
+) -> Result<Post, ApiError> {
+    let mut post =
+        objects::to_remote_post(actor_id, note).ok_or(bad_activity("the note has no id"))?;
+    if policy == Policy::RejectMedia {
+        post.attachments.clear();
+    }
+    Ok(post)
+}
+
+pub(super) fn actor_of(activity: &Value) -> &str {
+    activity
+        .get("actor")
+        .and_then(objects::id_of)
+        .unwrap_or_default()
+}
+
+/// The signer vouches for its own notes only, returns the note's id.
+pub(super) fn check_authored<'a>(note: &'a Value, actor_id: &str) -> Result<&'a str, ApiError> {
+    let note_id = objects::id_of(note).ok_or(bad_activity("the note has no id"))?;
+    if note.get("attributedTo").and_then(objects::id_of) != Some(actor_id)
+        || objects::host_of(note_id) != objects::host_of(actor_id)
+    {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "the note was not written by the activity's actor",
+        ));
+    }
+    Ok(note_id)
+}
+
+/// A post that is gone already needs no further change.
+fn to_inbox_result<T>(result: Result<T, PostError>) -> Result<StatusCode, ApiError> {
+    match result {
+        Ok(_) | Err(PostError::NotFound) => Ok(StatusCode::ACCEPTED),
+        Err(PostError::NotAuthor) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "only the author of a post may change it",
+        )),
+        Err(err) => Err(post_routes::to_status_code(err).into()),
+    }
+}
+
+fn unknown_reply_target() -> ApiError {
+    ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "only replies to threads of this board are accepted",
+    )
+}
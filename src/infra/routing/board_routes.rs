@@ -17,6 +17,7 @@ use crate::{
 pub(super) struct BoardView {
     pub(crate) board_id: String,
     pub(crate) name: String,
+    pub(crate) mirror_of: Option<String>,
 }
 
 pub(super) fn routes(app_state: AppState) -> Router {
@@ -60,12 +61,16 @@ fn to_view(board: Board) -> BoardView {
     BoardView {
         board_id: board.board_id.to_string(),
         name: board.name,
+        mirror_of: board.mirror.map(|mirror| mirror.actor_id),
     }
 }
 
-fn to_status_code(err: BoardError) -> StatusCode {
+pub(super) fn to_status_code(err: BoardError) -> StatusCode {
     match err {
         BoardError::NotFound => StatusCode::NOT_FOUND,
         BoardError::DbError => StatusCode::INTERNAL_SERVER_ERROR,
+        BoardError::InvalidName => StatusCode::BAD_REQUEST,
+        BoardError::AlreadyExists => StatusCode::CONFLICT,
+        BoardError::InvalidSettings(_) => StatusCode::BAD_REQUEST,
     }
 }
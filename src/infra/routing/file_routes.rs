@@ -1,14 +1,179 @@
-use axum::{Router, extract::Multipart, routing::post};
+use std::collections::HashMap;
 
-pub(super) fn routes() -> Router {
-    Router::new().route("/", post(upload_file))
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State, multipart::Field},
+    http::{StatusCode, header},
+    response::IntoResponse,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{BoardSettings, BoardUseCase},
+    infra::{AppState, DepenencyInjector, routing::api_error::ApiError},
+    media::{Media, MediaError, MediaUpload, MediaUseCase, UploadPolicy},
+};
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct MediaView {
+    pub(super) hash: String,
+    pub(super) url: String,
+    pub(super) size: usize,
+    pub(super) mime_type: String,
+    pub(super) width: Option<u32>,
+    pub(super) height: Option<u32>,
+    pub(super) thumbnail_url: Option<String>,
+    pub(super) duration: Option<f64>,
+    pub(super) codec: Option<String>,
+}
+
+pub(super) fn routes(app_state: AppState) -> Router {
+    Router::new()
+        // limits are enforced per board while reading, see read_field
+        .route("/", post(upload_file).layer(DefaultBodyLimit::disable()))
+        .route("/{file_key}", get(get_file))
+        .with_state(app_state)
+}
+
+/// Reads a multipart field into memory, refusing to buffer more than `max_size` bytes.
+pub(super) async fn read_field(mut field: Field<'_>, max_size: usize) -> Result<Vec<u8>, ApiError> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(|_| bad_multipart())? {
+        if data.len() + chunk.len() > max_size {
+            return Err(too_large(max_size));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+pub(super) fn upload_policy(settings: &BoardSettings) -> UploadPolicy {
+    UploadPolicy {
+        max_file_size: settings.max_file_size,
+        allowed_mime_types: settings.allowed_mime_types.clone(),
+        strip_metadata: !settings.keep_metadata,
+    }
+}
+
+pub(super) fn too_many_files(settings: &BoardSettings) -> ApiError {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!(
+            "at most {} file(s) may be attached",
+            settings.max_files_per_post
+        ),
+    )
+}
+
+pub(super) fn banned_file() -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, "this file is banned")
+}
+
+pub(super) fn bad_multipart() -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "malformed multipart body")
+}
+
+fn too_large(max_size: usize) -> ApiError {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("file exceeds the maximum size of {} bytes", max_size),
+    )
+}
+
+async fn upload_file(
+    State(di): State<DepenencyInjector>,
+    Query(params): Query<HashMap<String, String>>,
+    mut multipart: Multipart,
+) -> Result<Json<Vec<MediaView>>, ApiError> {
+    let settings = match params.get("board") {
+        Some(board_name) => match di.board_use_case().get_board_by_name(board_name).await {
+            Ok(board) => board.settings,
+            Err(_) => return Err(StatusCode::NOT_FOUND.into()),
+        },
+        None => BoardSettings::default(),
+    };
+    let policy = upload_policy(&settings);
+    let media_use_case = di.media_use_case();
+    let mut uploaded = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(|_| bad_multipart())? {
+        // only file inputs are stored, browsers send an empty part when none was selected
+        if field.name() != Some("file") || field.file_name().is_none_or(str::is_empty) {
+            continue;
+        }
+        if uploaded.len() == settings.max_files_per_post {
+            return Err(too_many_files(&settings));
+        }
+        let data = read_field(field, settings.max_file_size).await?;
+        match media_use_case
+            .upload_media(MediaUpload { data }, &policy)
+            .await
+        {
+            Ok(media) => uploaded.push(to_media_view(media)),
+            Err(err) => return Err(to_api_error(err, &policy)),
+        }
+    }
+    Ok(Json(uploaded))
+}
+
+async fn get_file(
+    State(di): State<DepenencyInjector>,
+    Path(file_key): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let media_use_case = di.media_use_case();
+    match media_use_case.get_media(&file_key).await {
+        Ok(stored) => Ok((
+            [
+                (header::CONTENT_TYPE, stored.mime_type),
+                (
+                    header::CACHE_CONTROL,
+                    "public, max-age=31536000, immutable".to_owned(),
+                ),
+            ],
+            stored.data,
+        )),
+        Err(MediaError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn to_media_view(media: Media) -> MediaView {
+    MediaView {
+        hash: media.hash,
+        url: media.url,
+        size: media.size,
+        mime_type: media.mime_type,
+        width: media.width,
+        height: media.height,
+        thumbnail_url: media.thumbnail_url,
+        duration: media.duration,
+        codec: media.codec,
+    }
 }
 
-async fn upload_file(mut multipart: Multipart) {
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        let name = field.name().unwrap().to_string();
-        let data = field.bytes().await.unwrap(); // check result for errors (e.g. 2MB maximum)
-        println!("Length of `{}` is {} bytes", name, data.len());
+pub(super) fn to_api_error(err: MediaError, policy: &UploadPolicy) -> ApiError {
+    match err {
+        MediaError::NotFound => StatusCode::NOT_FOUND.into(),
+        MediaError::StorageError => StatusCode::INTERNAL_SERVER_ERROR.into(),
+        MediaError::TooLarge => too_large(policy.max_file_size),
+        MediaError::UnsupportedType => ApiError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "file type not allowed, accepted types are: {}",
+                policy.allowed_mime_types.join(", ")
+            ),
+        ),
+        MediaError::UnsupportedCodec {
+            mime_type,
+            accepted,
+        } => ApiError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "codec not supported, accepted codecs for {} are: {}",
+                mime_type,
+                accepted.join(", ")
+            ),
+        ),
+        MediaError::Banned => banned_file(),
     }
-    // TODO: upload to store, return url
 }
@@ -1,20 +1,32 @@
 use std::collections::HashMap;
 
 use axum::{
-    Form, Json, Router,
-    extract::{Path, State},
+    Json, Router,
+    extract::{DefaultBodyLimit, Path, State},
     http::StatusCode,
     routing::{get, post},
 };
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 use crate::{
+    board::BoardUseCase,
     infra::{
         AppState,
-        routing::{board_routes::validate_board_name, thread_routes::parse_thread_id},
+        routing::{
+            api_error::ApiError,
+            board_routes::validate_board_name,
+            file_routes::banned_file,
+            post_submission::{
+                PostSubmission, attachments_of, duplicate_file, process_attachments,
+                store_attachments,
+            },
+            static_routes::SPOILER_THUMBNAIL_URL,
+            thread_routes::parse_thread_id,
+        },
     },
-    thread::{self, Post, PostError, PostUseCase, ThreadUseCase},
+    thread::{self, Attachment, Post, PostError, PostUseCase, ReactionKind, ThreadUseCase},
 };
 
 #[derive(Serialize, Deserialize)]
@@ -28,7 +40,30 @@ pub(super) struct PostView {
     pub(super) name: Option<String>,
     pub(super) subject: Option<String>,
     pub(super) content: Option<String>,
+    /// `text/plain` for local posts, `text/html` for the sanitized html of federated ones.
+    pub(super) content_type: String,
     pub(super) media_url: Option<String>,
+    pub(super) attachments: Vec<AttachmentView>,
+    pub(super) spoiler: bool,
+    pub(super) author: Option<String>, // actor id of a federated reply
+    pub(super) published: Option<DateTime<Utc>>,
+    pub(super) edited: Option<DateTime<Utc>>,
+    pub(super) deleted: Option<DateTime<Utc>>,
+    pub(super) likes: usize,
+    pub(super) announces: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct AttachmentView {
+    pub(super) hash: Option<String>,
+    pub(super) url: String,
+    pub(super) mime_type: String,
+    pub(super) size: usize,
+    pub(super) width: Option<u32>,
+    pub(super) height: Option<u32>,
+    pub(super) thumbnail_url: Option<String>,
+    pub(super) duration: Option<f64>,
+    pub(super) codec: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -37,12 +72,24 @@ pub(super) struct PostCreation {
     pub(super) subject: Option<String>,
     pub(super) content: Option<String>,
     pub(super) media_url: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub(super) spoiler: bool,
+}
+
+/// Html checkboxes submit `on` when checked and nothing otherwise.
+pub(super) fn is_checked(value: &str) -> bool {
+    matches!(value, "on" | "true" | "1")
+}
+
+fn deserialize_checkbox<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(is_checked(&value))
 }
 
 pub(super) fn routes(app_state: AppState) -> Router {
     Router::new()
         .route("/", get(get_posts))
-        .route("/", post(create_post))
+        .route("/", post(create_post).layer(DefaultBodyLimit::disable()))
         .route("/{post_id}", get(get_post))
         .with_state(app_state)
 }
@@ -53,7 +100,50 @@ pub(super) fn to_post_view(post: &Post) -> PostView {
         name: post.name.clone(),
         subject: post.subject.clone(),
         content: post.content.clone(),
+        content_type: match post.remote {
+            Some(_) => "text/html".to_owned(),
+            None => "text/plain".to_owned(),
+        },
         media_url: post.media_url.clone(),
+        attachments: post
+            .attachments
+            .iter()
+            .map(|attachment| to_attachment_view(attachment, post.spoiler))
+            .collect(),
+        spoiler: post.spoiler,
+        author: post.remote.as_ref().map(|remote| remote.actor_id.clone()),
+        published: post.published,
+        edited: post.edited,
+        deleted: post.deleted,
+        likes: count_reactions(post, ReactionKind::Like),
+        announces: count_reactions(post, ReactionKind::Announce),
+    }
+}
+
+fn count_reactions(post: &Post, kind: ReactionKind) -> usize {
+    post.reactions
+        .iter()
+        .filter(|reaction| reaction.kind == kind)
+        .count()
+}
+
+fn to_attachment_view(attachment: &Attachment, spoiler: bool) -> AttachmentView {
+    // the file itself stays reachable, only the preview is hidden
+    let thumbnail_url = if spoiler {
+        Some(SPOILER_THUMBNAIL_URL.to_owned())
+    } else {
+        attachment.thumbnail_url.clone()
+    };
+    AttachmentView {
+        hash: attachment.hash.clone(),
+        url: attachment.url.clone(),
+        mime_type: attachment.mime_type.clone(),
+        size: attachment.size,
+        width: attachment.width,
+        height: attachment.height,
+        thumbnail_url,
+        duration: attachment.duration,
+        codec: attachment.codec.clone(),
     }
 }
 
@@ -113,39 +203,77 @@ fn validate_post_id(params: &HashMap<String, String>) -> Result<Uuid, StatusCode
 async fn create_post(
     State(app_state): State<AppState>,
     Path(params): Path<HashMap<String, String>>,
-    Form(post_creation): Form<PostCreation>,
-) -> Result<Json<PostView>, StatusCode> {
+    submission: PostSubmission,
+) -> Result<Json<PostView>, ApiError> {
     let board_name = validate_board_name(&params)?;
-    let new_post = form_to_post(post_creation);
     let thread_id = parse_thread_id(&params)?;
+    let board = match app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(board_name)
+        .await
+    {
+        Ok(board) => board,
+        Err(_) => return Err(StatusCode::NOT_FOUND.into()),
+    };
     let thread_use_case = app_state.di.thread_use_case();
     let thread = match thread_use_case
         .get_thread_by_id(thread_id, board_name)
         .await
     {
-        Ok(thread) => thread,
-        Err(_) => return Err(StatusCode::NOT_FOUND),
+        // the board's rules and its federated ids only hold for its own threads
+        Ok(thread) if thread.board_id == board.board_id => thread,
+        _ => return Err(StatusCode::NOT_FOUND.into()),
     };
+    let (post_creation, media) =
+        process_attachments(&app_state.di, submission, &board.settings).await?;
+    let new_post = form_to_post(post_creation, attachments_of(&media));
     let post_use_case = app_state.di.post_use_case();
-    let created = post_use_case.post_into_thread(thread, new_post).await;
+    post_use_case
+        .validate_post(&board, &new_post)
+        .await
+        .map_err(to_api_error)?;
+    store_attachments(&app_state.di, media).await?;
+    let created = post_use_case
+        .post_into_thread(&board, thread, new_post)
+        .await;
     match created {
         Ok(post) => Ok(Json(to_post_view(&post))),
-        Err(err) => Err(to_status_code(err)),
+        Err(err) => Err(to_api_error(err)),
     }
 }
 
-fn form_to_post(post_creation: PostCreation) -> Post {
+fn form_to_post(post_creation: PostCreation, attachments: Vec<Attachment>) -> Post {
     Post {
         id: Uuid::new_v4(),
         name: post_creation.name,
         subject: post_creation.subject,
         content: post_creation.content,
         media_url: post_creation.media_url,
+        attachments,
+        spoiler: post_creation.spoiler,
+        remote: None,
+        published: Some(Utc::now()),
+        edited: None,
+        deleted: None,
+        reactions: Vec::new(),
+    }
+}
+
+fn to_api_error(err: PostError) -> ApiError {
+    match err {
+        PostError::DuplicateFile(original) => duplicate_file(original),
+        PostError::BannedFile => banned_file(),
+        err => to_status_code(err).into(),
     }
 }
 
-fn to_status_code(err: PostError) -> StatusCode {
+pub(super) fn to_status_code(err: PostError) -> StatusCode {
     match err {
         PostError::DbError => StatusCode::INTERNAL_SERVER_ERROR,
+        PostError::DuplicateFile(_) => StatusCode::CONFLICT,
+        PostError::BannedFile => StatusCode::FORBIDDEN,
+        PostError::NotFound => StatusCode::NOT_FOUND,
+        PostError::NotAuthor => StatusCode::FORBIDDEN,
     }
 }
@@ -0,0 +1,177 @@
+use axum::{
+    Form,
+    body::{self, Body},
+    extract::{FromRequest, Multipart, Request},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    board::BoardSettings,
+    infra::{
+        DepenencyInjector,
+        routing::{
+            api_error::ApiError,
+            file_routes::{bad_multipart, read_field, to_api_error, too_many_files, upload_policy},
+            post_routes::{PostCreation, is_checked},
+        },
+    },
+    media::{Media, MediaUpload, MediaUseCase, ProcessedMedia},
+    thread::Attachment,
+};
+use uuid::Uuid;
+
+/// Upper bound for url-encoded bodies and each text part of a multipart body.
+const MAX_TEXT_SIZE: usize = 64 * 1024;
+
+/// Multipart bodies are only read once the board is known, so its upload limits apply.
+pub(super) enum PostSubmission {
+    UrlEncoded(PostCreation),
+    Multipart(Multipart),
+}
+
+impl<S: Send + Sync> FromRequest<S> for PostSubmission {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+        if is_multipart {
+            let multipart = Multipart::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(PostSubmission::Multipart(multipart));
+        }
+        // the body limit is disabled for these routes, bound the form here instead
+        let (parts, body) = req.into_parts();
+        let bytes = body::to_bytes(body, MAX_TEXT_SIZE)
+            .await
+            .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+        let req = Request::from_parts(parts, Body::from(bytes));
+        let Form(post_creation) = Form::<PostCreation>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(PostSubmission::UrlEncoded(post_creation))
+    }
+}
+
+impl PostSubmission {
+    async fn read(
+        self,
+        settings: &BoardSettings,
+    ) -> Result<(PostCreation, Vec<MediaUpload>), ApiError> {
+        match self {
+            PostSubmission::UrlEncoded(post_creation) => Ok((post_creation, Vec::new())),
+            PostSubmission::Multipart(multipart) => read_multipart(multipart, settings).await,
+        }
+    }
+}
+
+async fn read_multipart(
+    mut multipart: Multipart,
+    settings: &BoardSettings,
+) -> Result<(PostCreation, Vec<MediaUpload>), ApiError> {
+    let mut post_creation = PostCreation {
+        name: None,
+        subject: None,
+        content: None,
+        media_url: None,
+        spoiler: false,
+    };
+    let mut files = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(|_| bad_multipart())? {
+        let field_name = field.name().unwrap_or_default().to_owned();
+        if field_name == "file" {
+            // browsers send an empty part when no file was selected
+            if field.file_name().is_none_or(str::is_empty) {
+                continue;
+            }
+            if files.len() == settings.max_files_per_post {
+                return Err(too_many_files(settings));
+            }
+            let data = read_field(field, settings.max_file_size).await?;
+            files.push(MediaUpload { data });
+            continue;
+        }
+        let text = read_field(field, MAX_TEXT_SIZE).await?;
+        let text = String::from_utf8(text).map_err(|_| bad_multipart())?;
+        let value = Some(text).filter(|t| !t.is_empty());
+        match field_name.as_str() {
+            "name" => post_creation.name = value,
+            "subject" => post_creation.subject = value,
+            "content" => post_creation.content = value,
+            "media_url" => post_creation.media_url = value,
+            "spoiler" => post_creation.spoiler = value.as_deref().is_some_and(is_checked),
+            _ => {} // ignore unknown fields like a form would
+        }
+    }
+    Ok((post_creation, files))
+}
+
+/// Points the post's media at the first file, nothing is stored yet, see [store_attachments].
+pub(super) async fn process_attachments(
+    di: &DepenencyInjector,
+    submission: PostSubmission,
+    settings: &BoardSettings,
+) -> Result<(PostCreation, Vec<ProcessedMedia>), ApiError> {
+    let (mut post_creation, files) = submission.read(settings).await?;
+    let policy = upload_policy(settings);
+    let media_use_case = di.media_use_case();
+    let mut processed = Vec::new();
+    for upload in files {
+        match media_use_case.process_media(upload, &policy).await {
+            Ok(media) => processed.push(media),
+            Err(err) => return Err(to_api_error(err, &policy)),
+        }
+    }
+    if let Some(first) = processed.first() {
+        post_creation.media_url = Some(first.media.url.clone());
+    }
+    Ok((post_creation, processed))
+}
+
+pub(super) fn attachments_of(processed: &[ProcessedMedia]) -> Vec<Attachment> {
+    processed
+        .iter()
+        .map(|processed| to_attachment(&processed.media))
+        .collect()
+}
+
+/// Writes the files once the post passed every check, so rejected posts leave nothing behind.
+pub(super) async fn store_attachments(
+    di: &DepenencyInjector,
+    processed: Vec<ProcessedMedia>,
+) -> Result<(), ApiError> {
+    let media_use_case = di.media_use_case();
+    for processed in processed {
+        if media_use_case.store_media(processed).await.is_err() {
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn duplicate_file(original: Uuid) -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        "this file has already been posted on this board",
+    )
+    .with_thread_id(original)
+}
+
+fn to_attachment(media: &Media) -> Attachment {
+    Attachment {
+        hash: Some(media.hash.clone()),
+        url: media.url.clone(),
+        mime_type: media.mime_type.clone(),
+        size: media.size,
+        width: media.width,
+        height: media.height,
+        thumbnail_url: media.thumbnail_url.clone(),
+        duration: media.duration,
+        codec: media.codec.clone(),
+    }
+}
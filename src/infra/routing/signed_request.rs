@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use axum::{
+    body,
+    extract::{FromRequest, FromRequestParts, OriginalUri, Path, Request},
+    http::StatusCode,
+};
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::{
+    federation::DeliveryUseCase,
+    infra::{
+        AppState,
+        activitypub::{
+            board_signer, objects,
+            signature::{self, SignatureError},
+        },
+        routing::{api_error::ApiError, board_routes},
+    },
+    moderation::{ModerationUseCase, Policy},
+};
+
+/// Activities are small, a megabyte leaves room for long posts.
+const MAX_ACTIVITY_SIZE: usize = 1024 * 1024;
+
+/// An activity whose HTTP signature and digest checked out, its `actor` is the signer.
+pub(super) struct SignedActivity {
+    pub(super) activity: Value,
+    /// What the admins decided about the signer's domain, blocked domains never get here.
+    pub(super) policy: Policy,
+}
+
+impl FromRequest<AppState> for SignedActivity {
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, app_state: &AppState) -> Result<Self, Self::Rejection> {
+        let (mut parts, body) = req.into_parts();
+        let params = signature::signature_params(&parts.headers).map_err(to_api_error)?;
+        let body = body::to_bytes(body, MAX_ACTIVITY_SIZE)
+            .await
+            .map_err(|_| ApiError::from(StatusCode::PAYLOAD_TOO_LARGE))?;
+        signature::check_coverage(&parts.headers, &params, !body.is_empty(), Utc::now())
+            .map_err(to_api_error)?;
+        if !body.is_empty() {
+            signature::verify_digest(&parts.headers, &body).map_err(to_api_error)?;
+        }
+        // nested routers strip their prefix, the sender signed the full path
+        let uri = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri,
+            None => &parts.uri,
+        };
+        let path_and_query = uri
+            .path_and_query()
+            .map_or(uri.path(), |path_and_query| path_and_query.as_str());
+        let signing_string =
+            signature::signing_string(&parts.method, path_and_query, &parts.headers, &params)
+                .ok_or(to_api_error(SignatureError::Incomplete))?;
+        // secure mode servers only hand out keys to signed requests, the board signs for us
+        let Path(path_params) =
+            Path::<HashMap<String, String>>::from_request_parts(&mut parts, app_state)
+                .await
+                .map_err(|_| ApiError::from(StatusCode::BAD_REQUEST))?;
+        let board_name = path_params
+            .get("board_name")
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        // blocked servers are turned away before we fetch anything from them
+        let host =
+            objects::host_of(&params.key_id).ok_or(to_api_error(SignatureError::Malformed))?;
+        let policy = app_state
+            .di
+            .moderation_use_case()
+            .get_policy_for_host(&host)
+            .await
+            .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))?;
+        if policy == Policy::Block {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "the sender's domain is blocked",
+            ));
+        }
+        let signer = board_signer(app_state, board_name)
+            .await
+            .map_err(board_routes::to_status_code)?;
+        let mut signer_actor = app_state
+            .remote_actors
+            .get_key_owner(&params.key_id, &signer)
+            .await
+            .ok_or(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "the signing key could not be fetched",
+            ))?;
+        if !signature::verify_signature(&params, &signing_string, &signer_actor.public_key_pem) {
+            // the actor may have rotated its key since we fetched it
+            signer_actor = match app_state
+                .remote_actors
+                .refetch_key_owner(&params.key_id, &signer)
+                .await
+            {
+                Some(actor)
+                    if signature::verify_signature(
+                        &params,
+                        &signing_string,
+                        &actor.public_key_pem,
+                    ) =>
+                {
+                    actor
+                }
+                _ => return Err(to_api_error(SignatureError::Invalid)),
+            };
+        }
+        let activity: Value =
+            serde_json::from_slice(&body).map_err(|_| ApiError::from(StatusCode::BAD_REQUEST))?;
+        // one actor may not speak for another
+        if activity.get("actor").and_then(objects::id_of) != Some(signer_actor.actor_id.as_str()) {
+            return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "the activity's actor did not sign it",
+            ));
+        }
+        // the sender's server is up, inboxes on it that were given up on get another try
+        app_state
+            .di
+            .delivery_use_case()
+            .revive_dead_host(&host)
+            .await
+            .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))?;
+        Ok(SignedActivity { activity, policy })
+    }
+}
+
+fn to_api_error(err: SignatureError) -> ApiError {
+    let message = match err {
+        SignatureError::Missing => "the request is not signed",
+        SignatureError::Malformed => "the signature header is malformed",
+        SignatureError::Incomplete => "the signature does not cover the target, date and digest",
+        SignatureError::Stale => "the request date is outside the accepted window",
+        SignatureError::DigestMismatch => "the digest does not match the body",
+        SignatureError::Invalid => "the signature does not verify",
+        SignatureError::KeyError => "the signing key is unusable",
+    };
+    ApiError::new(StatusCode::UNAUTHORIZED, message)
+}
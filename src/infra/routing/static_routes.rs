@@ -0,0 +1,20 @@
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+
+/// Shown instead of the thumbnail of spoilered attachments.
+pub(super) const SPOILER_THUMBNAIL_URL: &str = "/static/spoiler.svg";
+
+const SPOILER_SVG: &str = include_str!("../../../assets/spoiler.svg");
+
+pub(super) fn routes() -> Router {
+    Router::new().route("/spoiler.svg", get(get_spoiler))
+}
+
+async fn get_spoiler() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        SPOILER_SVG,
+    )
+}
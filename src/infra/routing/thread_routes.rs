@@ -1,13 +1,18 @@
 use crate::board::BoardUseCase;
 use crate::infra::AppState;
+use crate::infra::routing::api_error::ApiError;
 use crate::infra::routing::board_routes::validate_board_name;
+use crate::infra::routing::file_routes::banned_file;
 use crate::infra::routing::post_routes::{self, PostCreation, PostsView, to_post_view};
-use crate::thread::{Posts, Thread, ThreadCreation, ThreadError, ThreadUseCase};
+use crate::infra::routing::post_submission::{
+    PostSubmission, attachments_of, duplicate_file, process_attachments, store_attachments,
+};
+use crate::thread::{Attachment, Posts, Thread, ThreadCreation, ThreadError, ThreadUseCase};
 use axum::Router;
-use axum::extract::State;
+use axum::extract::{DefaultBodyLimit, State};
 use axum::http::StatusCode;
 use axum::routing::{get, post};
-use axum::{Form, Json, extract::Path};
+use axum::{Json, extract::Path};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -21,7 +26,7 @@ pub(super) struct ThreadView {
 pub(super) fn routes(app_state: AppState) -> Router {
     Router::new()
         .route("/", get(get_threads))
-        .route("/", post(create_thread))
+        .route("/", post(create_thread).layer(DefaultBodyLimit::disable()))
         .route("/{thread_id}", get(get_thread))
         .with_state(app_state.clone())
         .nest("/{thread_id}/posts", post_routes::routes(app_state))
@@ -72,32 +77,44 @@ async fn get_thread(
 async fn create_thread(
     State(app_state): State<AppState>,
     Path(params): Path<HashMap<String, String>>,
-    Form(post_creation): Form<PostCreation>,
-) -> Result<Json<ThreadView>, StatusCode> {
+    submission: PostSubmission,
+) -> Result<Json<ThreadView>, ApiError> {
     let board_name = validate_board_name(&params)?;
     let board_use_case = app_state.di.board_use_case();
     let thread_use_case = app_state.di.thread_use_case();
     let board = match board_use_case.get_board_by_name(board_name).await {
         Ok(board) => board,
-        Err(_) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::NOT_FOUND.into()),
     };
-    let thread_creation = to_thread_creation(post_creation);
-    let create_result = thread_use_case.create_thread(board, thread_creation).await;
-    match create_result {
+    let (post_creation, media) =
+        process_attachments(&app_state.di, submission, &board.settings).await?;
+    let thread_creation = to_thread_creation(post_creation, attachments_of(&media));
+    thread_use_case
+        .validate_thread(&board, &thread_creation)
+        .await
+        .map_err(to_api_error)?;
+    store_attachments(&app_state.di, media).await?;
+    match thread_use_case.create_thread(board, thread_creation).await {
         Ok(created) => {
             let view = to_thread_view(&created);
             Ok(Json(view))
         }
-        Err(err) => Err(to_status_code(err)),
+        Err(err) => Err(to_api_error(err)),
     }
 }
 
-fn to_thread_creation(post_creation: PostCreation) -> crate::thread::ThreadCreation {
+fn to_thread_creation(
+    post_creation: PostCreation,
+    attachments: Vec<Attachment>,
+) -> crate::thread::ThreadCreation {
     ThreadCreation {
         name: post_creation.name,
         subject: post_creation.subject,
         content: post_creation.content,
         media_url: post_creation.media_url,
+        attachments,
+        spoiler: post_creation.spoiler,
+        remote: None,
     }
 }
 
@@ -113,10 +130,25 @@ fn to_thread_view(thread: &Thread) -> ThreadView {
     }
 }
 
+pub(super) fn to_api_error(err: ThreadError) -> ApiError {
+    match err {
+        ThreadError::DuplicateFile(original) => duplicate_file(original),
+        ThreadError::BannedFile => banned_file(),
+        ThreadError::MirroredBoard => ApiError::new(
+            StatusCode::FORBIDDEN,
+            "threads of this board come from the group it mirrors",
+        ),
+        err => to_status_code(err).into(),
+    }
+}
+
 fn to_status_code(err: ThreadError) -> StatusCode {
     match err {
         ThreadError::NotFound => StatusCode::NOT_FOUND,
         ThreadError::DbError => StatusCode::INTERNAL_SERVER_ERROR,
         ThreadError::IdError => StatusCode::BAD_REQUEST,
+        ThreadError::DuplicateFile(_) => StatusCode::CONFLICT,
+        ThreadError::BannedFile => StatusCode::FORBIDDEN,
+        ThreadError::MirroredBoard => StatusCode::FORBIDDEN,
     }
 }
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, BoardUseCase},
+    infra::{
+        AppState, FederationConfig,
+        routing::{activitypub_routes::ACTIVITY_JSON, board_routes},
+    },
+    instance::{InstanceUsage, InstanceUseCase},
+};
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct JrdView {
+    pub(super) subject: String,
+    pub(super) aliases: Vec<String>,
+    pub(super) links: Vec<JrdLinkView>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct JrdLinkView {
+    pub(super) rel: String,
+    #[serde(rename = "type")]
+    pub(super) kind: String,
+    pub(super) href: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct NodeInfoLinksView {
+    pub(super) links: Vec<NodeInfoLinkView>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct NodeInfoLinkView {
+    pub(super) rel: String,
+    pub(super) href: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct NodeInfoView {
+    pub(super) version: String,
+    pub(super) software: SoftwareView,
+    pub(super) protocols: Vec<String>,
+    pub(super) services: ServicesView,
+    pub(super) open_registrations: bool,
+    pub(super) usage: UsageView,
+    pub(super) metadata: NodeMetadataView,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct SoftwareView {
+    pub(super) name: String,
+    pub(super) version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct ServicesView {
+    pub(super) inbound: Vec<String>,
+    pub(super) outbound: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct UsageView {
+    /// Posting is anonymous, there are no accounts to count.
+    pub(super) users: UsersView,
+    pub(super) local_posts: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct UsersView {}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct NodeMetadataView {
+    pub(super) boards: i64,
+    pub(super) threads: i64,
+}
+
+const NODEINFO_SCHEMA: &str = "http://nodeinfo.diaspora.software/ns/schema/2.1";
+
+pub(super) fn routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/webfinger", get(webfinger))
+        .route("/nodeinfo", get(get_nodeinfo_links))
+        .with_state(app_state)
+}
+
+/// The document `/.well-known/nodeinfo` links to.
+pub(super) fn nodeinfo_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/2.1", get(get_nodeinfo))
+        .with_state(app_state)
+}
+
+async fn webfinger(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    let resource = params.get("resource").ok_or(StatusCode::BAD_REQUEST)?;
+    let board_name = app_state
+        .federation
+        .parse_handle(resource)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let board = app_state
+        .di
+        .board_use_case()
+        .get_board_by_name(board_name)
+        .await
+        .map_err(board_routes::to_status_code)?;
+    let jrd = to_jrd_view(&app_state.federation, &board);
+    Ok(([(header::CONTENT_TYPE, "application/jrd+json")], Json(jrd)).into_response())
+}
+
+fn to_jrd_view(config: &FederationConfig, board: &Board) -> JrdView {
+    let actor_id = config.board_actor_id(&board.name);
+    JrdView {
+        subject: format!("acct:{}@{}", board.name, config.domain),
+        aliases: vec![actor_id.clone()],
+        links: vec![JrdLinkView {
+            rel: "self".to_owned(),
+            kind: ACTIVITY_JSON.to_owned(),
+            href: actor_id,
+        }],
+    }
+}
+
+async fn get_nodeinfo_links(State(app_state): State<AppState>) -> Json<NodeInfoLinksView> {
+    Json(NodeInfoLinksView {
+        links: vec![NodeInfoLinkView {
+            rel: NODEINFO_SCHEMA.to_owned(),
+            href: format!("{}/nodeinfo/2.1", app_state.federation.public_url),
+        }],
+    })
+}
+
+async fn get_nodeinfo(State(app_state): State<AppState>) -> Result<Response, StatusCode> {
+    let usage = match app_state.di.instance_use_case().get_usage().await {
+        Ok(usage) => usage,
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let content_type = format!("application/json; profile=\"{}#\"", NODEINFO_SCHEMA);
+    Ok((
+        [(header::CONTENT_TYPE, content_type)],
+        Json(to_nodeinfo_view(usage)),
+    )
+        .into_response())
+}
+
+fn to_nodeinfo_view(usage: InstanceUsage) -> NodeInfoView {
+    NodeInfoView {
+        version: "2.1".to_owned(),
+        software: SoftwareView {
+            name: env!("CARGO_PKG_NAME").to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+        },
+        protocols: vec!["activitypub".to_owned()],
+        services: ServicesView {
+            inbound: Vec::new(),
+            outbound: Vec::new(),
+        },
+        open_registrations: false,
+        usage: UsageView {
+            users: UsersView {},
+            local_posts: usage.posts,
+        },
+        metadata: NodeMetadataView {
+            boards: usage.boards,
+            threads: usage.threads,
+        },
+    }
+}
@@ -0,0 +1,65 @@
+mod local_media_store;
+mod s3_media_store;
+
+pub use local_media_store::LocalMediaStore;
+pub use s3_media_store::{S3Config, S3MediaStore};
+
+use crate::media::{MediaError, MediaStore};
+
+/// The media store picked by configuration, `MEDIA_BACKEND` is either `local` or `s3`.
+#[derive(Clone)]
+pub enum MediaBackend {
+    Local(LocalMediaStore),
+    S3(S3MediaStore),
+}
+
+impl MediaStore for MediaBackend {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaError> {
+        match self {
+            MediaBackend::Local(store) => store.put(key, data).await,
+            MediaBackend::S3(store) => store.put(key, data).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaError> {
+        match self {
+            MediaBackend::Local(store) => store.get(key).await,
+            MediaBackend::S3(store) => store.get(key).await,
+        }
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        match self {
+            MediaBackend::Local(store) => store.url_for(key),
+            MediaBackend::S3(store) => store.url_for(key),
+        }
+    }
+}
+
+pub(crate) fn init_media_backend() -> MediaBackend {
+    let backend: String = dotenvy::var("MEDIA_BACKEND").unwrap_or("local".to_owned());
+    // without a public url media is served through the api
+    let public_url: String = dotenvy::var("MEDIA_PUBLIC_URL").unwrap_or("/api/files".to_owned());
+    match backend.as_str() {
+        "local" => {
+            let media_path: String = dotenvy::var("MEDIA_PATH").unwrap_or("media".to_owned());
+            MediaBackend::Local(LocalMediaStore::new(media_path.into(), public_url))
+        }
+        "s3" => {
+            let config = S3Config {
+                endpoint: required_var("S3_ENDPOINT"),
+                region: dotenvy::var("S3_REGION").unwrap_or("us-east-1".to_owned()),
+                bucket: required_var("S3_BUCKET"),
+                access_key: required_var("S3_ACCESS_KEY"),
+                secret_key: required_var("S3_SECRET_KEY"),
+                path_style: dotenvy::var("S3_PATH_STYLE").is_ok_and(|v| v == "true"),
+            };
+            MediaBackend::S3(S3MediaStore::new(config, public_url))
+        }
+        other => panic!("Unknown MEDIA_BACKEND {}, expected local or s3", other),
+    }
+}
+
+fn required_var(name: &str) -> String {
+    dotenvy::var(name).unwrap_or_else(|_| panic!("Env var {} is required for s3 media.", name))
+}
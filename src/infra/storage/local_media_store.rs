@@ -0,0 +1,62 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+use crate::media::{MediaError, MediaStore};
+
+#[derive(Clone)]
+pub struct LocalMediaStore {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl LocalMediaStore {
+    pub fn new(root: PathBuf, public_url: String) -> Self {
+        Self { root, public_url }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        // fan out into two directory levels to keep directories small
+        self.root.join(&key[0..2]).join(&key[2..4]).join(key)
+    }
+}
+
+impl MediaStore for LocalMediaStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaError> {
+        let path = self.path_for(key);
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(());
+        }
+        let dir = match path.parent() {
+            Some(dir) => dir,
+            None => return Err(MediaError::StorageError),
+        };
+        if tokio::fs::create_dir_all(dir).await.is_err() {
+            return Err(MediaError::StorageError);
+        }
+        let tmp_path = dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        if tokio::fs::write(&tmp_path, data).await.is_err() {
+            return Err(MediaError::StorageError);
+        }
+        match tokio::fs::rename(&tmp_path, &path).await {
+            Ok(_) => Ok(()),
+            Err(_) => {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                Err(MediaError::StorageError)
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaError> {
+        match tokio::fs::read(self.path_for(key)).await {
+            Ok(data) => Ok(data),
+            Err(err) if err.kind() == ErrorKind::NotFound => Err(MediaError::NotFound),
+            Err(_) => Err(MediaError::StorageError),
+        }
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url.trim_end_matches('/'), key)
+    }
+}
@@ -0,0 +1,171 @@
+use s3::{Bucket, Region, creds::Credentials, error::S3Error};
+
+use crate::media::{MediaError, MediaStore, mime_type_for};
+
+pub struct S3Config {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub path_style: bool,
+}
+
+#[derive(Clone)]
+pub struct S3MediaStore {
+    bucket: Box<Bucket>,
+    public_url: String,
+}
+
+impl S3MediaStore {
+    pub fn new(config: S3Config, public_url: String) -> Self {
+        let region = Region::Custom {
+            region: config.region,
+            endpoint: config.endpoint,
+        };
+        let credentials = Credentials::new(
+            Some(&config.access_key),
+            Some(&config.secret_key),
+            None,
+            None,
+            None,
+        )
+        .expect("Invalid S3 credentials");
+        let bucket =
+            Bucket::new(&config.bucket, region, credentials).expect("Invalid S3 bucket config");
+        let bucket = if config.path_style {
+            bucket.with_path_style()
+        } else {
+            bucket
+        };
+        Self { bucket, public_url }
+    }
+}
+
+impl MediaStore for S3MediaStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaError> {
+        // only a missing object is written, any other failure to look it up is an error
+        match self.bucket.head_object(key).await {
+            Ok((_, status)) if is_success(status) => return Ok(()),
+            Ok((_, 404)) | Err(S3Error::HttpFailWithBody(404, _)) => {}
+            _ => return Err(MediaError::StorageError),
+        }
+        let response = self
+            .bucket
+            .put_object_with_content_type(key, data, mime_type_for(key))
+            .await;
+        match response {
+            Ok(response) if is_success(response.status_code()) => Ok(()),
+            _ => Err(MediaError::StorageError),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaError> {
+        match self.bucket.get_object(key).await {
+            Ok(response) if response.status_code() == 200 => Ok(response.to_vec()),
+            Ok(response) if response.status_code() == 404 => Err(MediaError::NotFound),
+            Err(S3Error::HttpFailWithBody(404, _)) => Err(MediaError::NotFound),
+            _ => Err(MediaError::StorageError),
+        }
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url.trim_end_matches('/'), key)
+    }
+}
+
+fn is_success(status: u16) -> bool {
+    (200..300).contains(&status)
+}
+
+#[cfg(test)]
+mod tests {
+    use s3::BucketConfiguration;
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// The store at `S3_ENDPOINT`, a local MinIO by default credentials.
+    fn config(endpoint: String, path_style: bool) -> S3Config {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or(default.to_owned());
+        S3Config {
+            endpoint,
+            region: var("S3_REGION", "us-east-1"),
+            bucket: var("S3_BUCKET", "fediboard-test"),
+            access_key: var("S3_ACCESS_KEY", "minioadmin"),
+            secret_key: var("S3_SECRET_KEY", "minioadmin"),
+            path_style,
+        }
+    }
+
+    async fn create_bucket(config: &S3Config) {
+        let region = Region::Custom {
+            region: config.region.clone(),
+            endpoint: config.endpoint.clone(),
+        };
+        let credentials = Credentials::new(
+            Some(&config.access_key),
+            Some(&config.secret_key),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let bucket = Bucket::new(&config.bucket, region.clone(), credentials.clone())
+            .unwrap()
+            .with_path_style();
+        if !bucket.exists().await.unwrap() {
+            let config = BucketConfiguration::default();
+            let response =
+                Bucket::create_with_path_style(&bucket.name, region, credentials, config)
+                    .await
+                    .unwrap();
+            // AWS and some emulators refuse a us-east-1 location constraint
+            assert!(
+                response.success(),
+                "could not create the bucket, try RUST_S3_SKIP_LOCATION_CONSTRAINT=true: {}",
+                response.response_text
+            );
+        }
+    }
+
+    #[test]
+    fn builds_urls_from_the_public_url() {
+        for path_style in [true, false] {
+            let config = config("http://localhost:9000".to_owned(), path_style);
+            let store = S3MediaStore::new(config, "https://media.example.org/".to_owned());
+            assert_eq!(
+                store.url_for("ab/abcdef.png"),
+                "https://media.example.org/ab/abcdef.png"
+            );
+        }
+    }
+
+    /// Run with `cargo test -- --ignored` against the store at `S3_ENDPOINT`.
+    /// `S3_PATH_STYLE=false` tests virtual-hosted requests, the bucket's host name has to
+    /// resolve for those, and MinIO needs `MINIO_DOMAIN`.
+    #[tokio::test]
+    #[ignore = "needs an S3 compatible store at S3_ENDPOINT"]
+    async fn stores_media() {
+        let endpoint = std::env::var("S3_ENDPOINT").expect("S3_ENDPOINT is not set");
+        let path_style = std::env::var("S3_PATH_STYLE").map_or(true, |v| v == "true");
+        let config = config(endpoint, path_style);
+        create_bucket(&config).await;
+        let store = S3MediaStore::new(config, "/api/files".to_owned());
+        let key = format!("test/{}.png", Uuid::new_v4());
+
+        assert!(matches!(store.get(&key).await, Err(MediaError::NotFound)));
+        assert!(store.put(&key, b"original").await.is_ok());
+        assert_eq!(
+            store.get(&key).await.ok().as_deref(),
+            Some(&b"original"[..])
+        );
+
+        assert!(store.put(&key, b"replaced").await.is_ok());
+        assert_eq!(
+            store.get(&key).await.ok().as_deref(),
+            Some(&b"original"[..])
+        );
+        assert_eq!(store.url_for(&key), format!("/api/files/{}", key));
+    }
+}
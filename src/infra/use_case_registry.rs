@@ -2,13 +2,26 @@ use sqlx::PgPool;
 
 use crate::{
     board::{self, BoardUseCase},
-    infra::persistence::{BoardPgPersistence, ThreadPgPersistence},
+    federation::{
+        self, DeadHosts, DeliveryUseCase, FederationUseCase, Publisher, RemoteActorUseCase,
+    },
+    infra::persistence::{
+        BoardPgPersistence, DeliveryPgPersistence, FederationPgPersistence, InstancePgPersistence,
+        ModerationPgPersistence, RemoteActorPgPersistence, ThreadPgPersistence,
+    },
+    infra::{FederationConfig, activitypub::publisher::ActivityPublisher, storage::MediaBackend},
+    instance::{self, InstanceUseCase},
+    media::{self, MediaUseCase},
+    moderation::{self, ModerationUseCase},
     thread::{PostUseCase, ThreadUseCase},
 };
 
 #[derive(Clone)]
 pub struct UseCaseRegistry {
     db_pool: sqlx::Pool<sqlx::Postgres>,
+    media_backend: MediaBackend,
+    federation: FederationConfig,
+    dead_hosts: DeadHosts,
 }
 
 impl UseCaseRegistry {
@@ -17,14 +30,71 @@ impl UseCaseRegistry {
     }
 
     pub fn thread_use_case(&self) -> impl ThreadUseCase {
-        crate::thread::thread_use_case(ThreadPgPersistence::new(self.db_pool.clone()))
+        crate::thread::thread_use_case(
+            ThreadPgPersistence::new(self.db_pool.clone()),
+            ModerationPgPersistence::new(self.db_pool.clone()),
+            self.publisher(),
+        )
     }
 
     pub fn post_use_case(&self) -> impl PostUseCase {
-        crate::thread::post_use_case(ThreadPgPersistence::new(self.db_pool.clone()))
+        crate::thread::post_use_case(
+            ThreadPgPersistence::new(self.db_pool.clone()),
+            ModerationPgPersistence::new(self.db_pool.clone()),
+            self.publisher(),
+        )
+    }
+
+    pub fn media_use_case(&self) -> impl MediaUseCase {
+        media::media_use_case(
+            self.media_backend.clone(),
+            ModerationPgPersistence::new(self.db_pool.clone()),
+        )
+    }
+
+    pub fn moderation_use_case(&self) -> impl ModerationUseCase {
+        moderation::moderation_use_case(ModerationPgPersistence::new(self.db_pool.clone()))
+    }
+
+    pub fn federation_use_case(&self) -> impl FederationUseCase {
+        federation::federation_use_case(FederationPgPersistence::new(self.db_pool.clone()))
+    }
+
+    pub fn delivery_use_case(&self) -> impl DeliveryUseCase {
+        federation::delivery_use_case(
+            DeliveryPgPersistence::new(self.db_pool.clone()),
+            ModerationPgPersistence::new(self.db_pool.clone()),
+            self.dead_hosts.clone(),
+        )
+    }
+
+    fn remote_actor_use_case(&self) -> impl RemoteActorUseCase {
+        federation::remote_actor_use_case(RemoteActorPgPersistence::new(self.db_pool.clone()))
+    }
+
+    pub fn instance_use_case(&self) -> impl InstanceUseCase {
+        instance::instance_use_case(InstancePgPersistence::new(self.db_pool.clone()))
+    }
+
+    fn publisher(&self) -> impl Publisher + Sync {
+        ActivityPublisher::new(
+            self.federation.clone(),
+            self.federation_use_case(),
+            self.delivery_use_case(),
+            self.remote_actor_use_case(),
+        )
     }
 }
 
-pub fn build_registry(db_pool: PgPool) -> UseCaseRegistry {
-    UseCaseRegistry { db_pool }
+pub fn build_registry(
+    db_pool: PgPool,
+    media_backend: MediaBackend,
+    federation: FederationConfig,
+) -> UseCaseRegistry {
+    UseCaseRegistry {
+        db_pool,
+        media_backend,
+        federation,
+        dead_hosts: DeadHosts::default(),
+    }
 }
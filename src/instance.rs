@@ -0,0 +1,12 @@
+mod instance_use_case;
+
+pub use instance_use_case::{
+    InstanceError, InstancePersistence, InstanceUseCase, instance_use_case,
+};
+
+/// What this instance hosts, reported to fediverse crawlers.
+pub struct InstanceUsage {
+    pub boards: i64,
+    pub threads: i64,
+    pub posts: i64, // federated replies are not counted
+}
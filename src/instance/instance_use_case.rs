@@ -0,0 +1,30 @@
+use super::InstanceUsage;
+
+pub enum InstanceError {
+    DbError,
+}
+
+pub trait InstancePersistence {
+    fn count_usage(&self) -> impl Future<Output = Result<InstanceUsage, InstanceError>> + Send;
+}
+
+pub trait InstanceUseCase {
+    fn get_usage(&self) -> impl Future<Output = Result<InstanceUsage, InstanceError>> + Send;
+}
+
+pub fn instance_use_case(persistence: impl InstancePersistence + Sync) -> impl InstanceUseCase {
+    InstanceUseCaseImpl { persistence }
+}
+
+struct InstanceUseCaseImpl<T>
+where
+    T: InstancePersistence,
+{
+    persistence: T,
+}
+
+impl<T: InstancePersistence + Sync> InstanceUseCase for InstanceUseCaseImpl<T> {
+    async fn get_usage(&self) -> Result<InstanceUsage, InstanceError> {
+        self.persistence.count_usage().await
+    }
+}
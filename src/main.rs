@@ -1,5 +1,9 @@
 mod board;
+mod federation;
 mod infra;
+mod instance;
+mod media;
+mod moderation;
 mod thread;
 
 #[tokio::main]
@@ -0,0 +1,82 @@
+mod media_use_case;
+mod metadata;
+mod probe;
+mod thumbnail;
+
+pub use media_use_case::{MediaError, MediaStore, MediaUseCase, media_use_case};
+
+/// Every type uploads are probed and stored as, boards allow a subset of them.
+pub const SUPPORTED_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "video/webm",
+    "video/mp4",
+    "audio/mpeg",
+];
+
+pub struct MediaUpload {
+    pub data: Vec<u8>,
+}
+
+pub struct UploadPolicy {
+    pub max_file_size: usize,
+    pub allowed_mime_types: Vec<String>,
+    pub strip_metadata: bool,
+}
+
+pub struct Media {
+    /// SHA-256 of the file as uploaded, before any processing.
+    pub hash: String,
+    pub url: String,
+    pub size: usize,
+    pub mime_type: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub thumbnail_url: Option<String>,
+    pub duration: Option<f64>,
+    pub codec: Option<String>,
+}
+
+/// An upload that passed every check, its files are only written by [MediaUseCase::store_media].
+pub struct ProcessedMedia {
+    pub media: Media,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+pub struct StoredMedia {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// Detects the type from the file's magic bytes, never trusting what the client claims.
+pub fn detect_mime_type(data: &[u8]) -> Option<&'static str> {
+    infer::get(data).map(|kind| kind.mime_type())
+}
+
+pub fn extension_for(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "video/webm" => "webm",
+        "video/mp4" => "mp4",
+        "audio/mpeg" => "mp3",
+        _ => "bin",
+    }
+}
+
+pub fn mime_type_for(key: &str) -> &'static str {
+    match key.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("jpg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("webm") => "video/webm",
+        Some("mp4") => "video/mp4",
+        Some("mp3") => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
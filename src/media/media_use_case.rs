@@ -0,0 +1,231 @@
+use sha2::{Digest, Sha256};
+
+use crate::moderation::{ModerationPersistence, is_hash_banned};
+
+use super::metadata;
+use super::probe::{self, AvInfo, ProbeError};
+use super::thumbnail::{self, ImageInfo};
+use super::{
+    Media, MediaUpload, ProcessedMedia, StoredMedia, UploadPolicy, detect_mime_type, extension_for,
+    mime_type_for,
+};
+
+pub enum MediaError {
+    NotFound,
+    StorageError,
+    TooLarge,
+    UnsupportedType,
+    /// The container is fine but a track is not, with what the container may hold instead.
+    UnsupportedCodec {
+        mime_type: &'static str,
+        accepted: &'static [&'static str],
+    },
+    Banned,
+}
+
+/// Keys are content addressed, the bytes behind a key never change, so existing keys are not
+/// written again and files can be cached forever.
+pub trait MediaStore {
+    fn put(&self, key: &str, data: &[u8]) -> impl Future<Output = Result<(), MediaError>> + Send;
+
+    fn get(&self, key: &str) -> impl Future<Output = Result<Vec<u8>, MediaError>> + Send;
+
+    fn url_for(&self, key: &str) -> String;
+}
+
+pub trait MediaUseCase {
+    /// Checks and processes the upload without storing anything, urls are already known.
+    fn process_media(
+        &self,
+        upload: MediaUpload,
+        policy: &UploadPolicy,
+    ) -> impl Future<Output = Result<ProcessedMedia, MediaError>> + Send;
+
+    fn store_media(
+        &self,
+        processed: ProcessedMedia,
+    ) -> impl Future<Output = Result<Media, MediaError>> + Send;
+
+    fn upload_media(
+        &self,
+        upload: MediaUpload,
+        policy: &UploadPolicy,
+    ) -> impl Future<Output = Result<Media, MediaError>> + Send;
+
+    fn get_media(&self, key: &str) -> impl Future<Output = Result<StoredMedia, MediaError>> + Send;
+}
+
+pub fn media_use_case(
+    store: impl MediaStore + Sync,
+    moderation_persistence: impl ModerationPersistence + Sync,
+) -> impl MediaUseCase {
+    MediaUseCaseImpl {
+        store,
+        moderation_persistence,
+    }
+}
+
+struct MediaUseCaseImpl<T, M>
+where
+    T: MediaStore,
+    M: ModerationPersistence,
+{
+    store: T,
+    moderation_persistence: M,
+}
+
+impl<T: MediaStore + Sync, M: ModerationPersistence + Sync> MediaUseCase
+    for MediaUseCaseImpl<T, M>
+{
+    async fn process_media(
+        &self,
+        upload: MediaUpload,
+        policy: &UploadPolicy,
+    ) -> Result<ProcessedMedia, MediaError> {
+        if upload.data.len() > policy.max_file_size {
+            return Err(MediaError::TooLarge);
+        }
+        let hash = sha256_hex(&upload.data);
+        match is_hash_banned(&self.moderation_persistence, &hash).await {
+            Ok(false) => {}
+            Ok(true) => return Err(MediaError::Banned),
+            Err(_) => return Err(MediaError::StorageError),
+        }
+        let mime_type = match detect_mime_type(&upload.data) {
+            Some(mime_type) if policy.allowed_mime_types.iter().any(|m| m == mime_type) => {
+                mime_type
+            }
+            _ => return Err(MediaError::UnsupportedType),
+        };
+        let data = upload.data;
+        let (data, av_info) = if probe::is_audio_or_video(mime_type) {
+            match probe::probe_in_background(data, mime_type).await {
+                Ok((data, av_info)) => (data, Some(av_info)),
+                Err(ProbeError::Malformed) => return Err(MediaError::UnsupportedType),
+                Err(ProbeError::UnsupportedCodec) => {
+                    return Err(MediaError::UnsupportedCodec {
+                        mime_type,
+                        accepted: probe::accepted_codecs(mime_type),
+                    });
+                }
+            }
+        } else {
+            (data, None)
+        };
+        let (data, image_info) = if mime_type.starts_with("image/") {
+            let strip = policy.strip_metadata && metadata::supports_stripping(mime_type);
+            // decoding and re-encoding is cpu bound, keep it off the async workers
+            tokio::task::spawn_blocking(move || {
+                let data = if strip {
+                    metadata::strip_metadata(&data, mime_type)?
+                } else {
+                    data
+                };
+                let image_info = thumbnail::generate_thumbnail(&data);
+                Some((data, image_info))
+            })
+            .await
+            .map_err(|_| MediaError::StorageError)?
+            .ok_or(MediaError::UnsupportedType)? // claims to be an image but does not decode
+        } else {
+            (data, None)
+        };
+        // re-encoding may grow the file past what the board takes
+        if data.len() > policy.max_file_size {
+            return Err(MediaError::TooLarge);
+        }
+        // stripping or re-encoding changes the hash, the stored one is what admins see in urls
+        let stored_hash = sha256_hex(&data);
+        match is_hash_banned(&self.moderation_persistence, &stored_hash).await {
+            Ok(false) => {}
+            Ok(true) => return Err(MediaError::Banned),
+            Err(_) => return Err(MediaError::StorageError),
+        }
+        let key = format!("{}.{}", stored_hash, extension_for(mime_type));
+        let mut media = Media {
+            hash,
+            url: self.store.url_for(&key),
+            size: data.len(),
+            mime_type: mime_type.to_owned(),
+            width: None,
+            height: None,
+            thumbnail_url: None,
+            duration: None,
+            codec: None,
+        };
+        let mut thumbnail_file = None;
+        if let Some(ImageInfo {
+            width,
+            height,
+            thumbnail,
+        }) = image_info
+        {
+            let thumbnail_key = content_key(&thumbnail.data, thumbnail.mime_type);
+            media.width = Some(width);
+            media.height = Some(height);
+            media.thumbnail_url = Some(self.store.url_for(&thumbnail_key));
+            thumbnail_file = Some((thumbnail_key, thumbnail.data));
+        }
+        if let Some(AvInfo {
+            duration,
+            width,
+            height,
+            codec,
+        }) = av_info
+        {
+            media.duration = duration;
+            media.width = width;
+            media.height = height;
+            media.codec = Some(codec);
+        }
+        let files = std::iter::once((key, data)).chain(thumbnail_file).collect();
+        Ok(ProcessedMedia { media, files })
+    }
+
+    async fn store_media(&self, processed: ProcessedMedia) -> Result<Media, MediaError> {
+        for (key, data) in &processed.files {
+            self.store.put(key, data).await?;
+        }
+        Ok(processed.media)
+    }
+
+    async fn upload_media(
+        &self,
+        upload: MediaUpload,
+        policy: &UploadPolicy,
+    ) -> Result<Media, MediaError> {
+        let processed = self.process_media(upload, policy).await?;
+        self.store_media(processed).await
+    }
+
+    async fn get_media(&self, key: &str) -> Result<StoredMedia, MediaError> {
+        if !is_valid_key(key) {
+            return Err(MediaError::NotFound);
+        }
+        let data = self.store.get(key).await?;
+        Ok(StoredMedia {
+            data,
+            mime_type: mime_type_for(key).to_owned(),
+        })
+    }
+}
+
+fn content_key(data: &[u8], mime_type: &str) -> String {
+    format!("{}.{}", sha256_hex(data), extension_for(mime_type))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    match key.split_once('.') {
+        Some((hash, ext)) => {
+            hash.len() == 64
+                && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+                && !ext.is_empty()
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+
+const JPEG_QUALITY: u8 = 90;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const PNG_METADATA_CHUNKS: [&[u8]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+const WEBP_METADATA_CHUNKS: [&[u8]; 2] = [b"EXIF", b"XMP "];
+
+/// The exif and xmp bits of the `VP8X` flags, cleared along with their chunks.
+const WEBP_METADATA_FLAGS: u8 = 0x08 | 0x04;
+
+/// Decodes an image with its exif orientation already applied to the pixels.
+pub(super) fn decode_oriented(data: &[u8]) -> Option<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    Some(image)
+}
+
+pub(super) fn supports_stripping(mime_type: &str) -> bool {
+    matches!(mime_type, "image/jpeg" | "image/png" | "image/webp")
+}
+
+/// Re-encodes the image from its pixels alone, which drops exif, xmp and iptc data.
+/// Animations would lose all but their first frame, their metadata chunks are dropped instead.
+pub(super) fn strip_metadata(data: &[u8], mime_type: &str) -> Option<Vec<u8>> {
+    match mime_type {
+        "image/png" if png_chunks(data)?.iter().any(|(kind, _)| *kind == b"acTL") => {
+            return Some(strip_png_chunks(data));
+        }
+        "image/webp" if webp_chunks(data)?.iter().any(|(kind, _)| *kind == b"ANIM") => {
+            return Some(strip_webp_chunks(data));
+        }
+        _ => {}
+    }
+    let image = decode_oriented(data)?;
+    let mut encoded = Vec::new();
+    let result = match mime_type {
+        "image/jpeg" => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY)),
+        "image/png" => image.write_with_encoder(PngEncoder::new(&mut encoded)),
+        "image/webp" => {
+            // the webp encoder only takes 8 bit rgb(a)
+            let image = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            image.write_with_encoder(WebPEncoder::new_lossless(&mut encoded))
+        }
+        _ => return None,
+    };
+    result.ok().map(|_| encoded)
+}
+
+/// Splits a png into `(type, whole chunk)` pairs up to `IEND`, anything after it is dropped.
+fn png_chunks(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE)?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let chunk = rest.get(..length.checked_add(12)?)?;
+        chunks.push((&chunk[4..8], chunk));
+        if &chunk[4..8] == b"IEND" {
+            break;
+        }
+        rest = &rest[chunk.len()..];
+    }
+    Some(chunks)
+}
+
+/// Splits the chunks of a RIFF WebP into `(fourcc, whole chunk)` pairs, padding included.
+fn webp_chunks(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    let riff_size = u32::from_le_bytes(data[4..8].try_into().ok()?) as usize;
+    let mut rest = data.get(12..riff_size.checked_add(8)?.min(data.len()))?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let size = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?) as usize;
+        let end = size.checked_add(8)?;
+        rest.get(..end)?;
+        // chunks are padded to an even size, the last one may leave its padding out
+        let chunk = &rest[..(end + size % 2).min(rest.len())];
+        chunks.push((&chunk[..4], chunk));
+        rest = &rest[chunk.len()..];
+    }
+    Some(chunks)
+}
+
+fn strip_png_chunks(data: &[u8]) -> Vec<u8> {
+    let mut stripped = PNG_SIGNATURE.to_vec();
+    for (kind, chunk) in png_chunks(data).unwrap_or_default() {
+        if !PNG_METADATA_CHUNKS.contains(&kind) {
+            stripped.extend_from_slice(chunk);
+        }
+    }
+    stripped
+}
+
+fn strip_webp_chunks(data: &[u8]) -> Vec<u8> {
+    let mut chunks = Vec::new();
+    for (kind, chunk) in webp_chunks(data).unwrap_or_default() {
+        if WEBP_METADATA_CHUNKS.contains(&kind) {
+            continue;
+        }
+        let start = chunks.len();
+        chunks.extend_from_slice(chunk);
+        if kind == b"VP8X" && chunk.len() > 8 {
+            chunks[start + 8] &= !WEBP_METADATA_FLAGS;
+        }
+        if chunk.len() % 2 == 1 {
+            chunks.push(0);
+        }
+    }
+    let mut stripped = b"RIFF".to_vec();
+    stripped.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    stripped.extend_from_slice(b"WEBP");
+    stripped.extend_from_slice(&chunks);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]); // the crc is not checked
+        chunk
+    }
+
+    fn webp_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend_from_slice(&body);
+        data
+    }
+
+    #[test]
+    fn keeps_the_frames_of_animated_pngs() {
+        let header = png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        let animation = png_chunk(b"acTL", &[0, 0, 0, 2, 0, 0, 0, 0]);
+        let frame = png_chunk(b"fcTL", &[1; 26]);
+        let pixels = png_chunk(b"IDAT", &[2; 10]);
+        let second_frame = png_chunk(b"fdAT", &[3; 14]);
+        let end = png_chunk(b"IEND", &[]);
+        let [exif, text] = [
+            png_chunk(b"eXIf", b"MM\0*gps"),
+            png_chunk(b"tEXt", b"Author\0someone"),
+        ];
+        let data = [
+            PNG_SIGNATURE.to_vec(),
+            header.clone(),
+            exif,
+            animation.clone(),
+            text,
+            frame.clone(),
+            pixels.clone(),
+            second_frame.clone(),
+            end.clone(),
+            b"trailing".to_vec(),
+        ]
+        .concat();
+        let expected = [
+            PNG_SIGNATURE.to_vec(),
+            header,
+            animation,
+            frame,
+            pixels,
+            second_frame,
+            end,
+        ]
+        .concat();
+        assert_eq!(strip_metadata(&data, "image/png"), Some(expected));
+    }
+
+    #[test]
+    fn keeps_the_frames_of_animated_webps() {
+        let extended = |flags: u8| webp_chunk(b"VP8X", &[flags, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let animation = webp_chunk(b"ANIM", &[0; 6]);
+        let frames = [webp_chunk(b"ANMF", &[1; 17]), webp_chunk(b"ANMF", &[2; 20])];
+        let data = riff(&[
+            extended(0x02 | 0x08 | 0x04),
+            animation.clone(),
+            frames[0].clone(),
+            frames[1].clone(),
+            webp_chunk(b"EXIF", b"MM\0*gps"),
+            webp_chunk(b"XMP ", b"<x:xmpmeta/>"),
+        ]);
+        let expected = riff(&[
+            extended(0x02),
+            animation,
+            frames[0].clone(),
+            frames[1].clone(),
+        ]);
+        assert_eq!(strip_metadata(&data, "image/webp"), Some(expected));
+    }
+
+    #[test]
+    fn refuses_truncated_chunks() {
+        let mut png = [
+            PNG_SIGNATURE.to_vec(),
+            png_chunk(b"acTL", &[0; 8]),
+            png_chunk(b"IDAT", &[0; 10]),
+        ]
+        .concat();
+        png.truncate(png.len() - 3);
+        assert_eq!(strip_metadata(&png, "image/png"), None);
+        let mut webp = riff(&[webp_chunk(b"ANIM", &[0; 6]), webp_chunk(b"ANMF", &[0; 20])]);
+        webp.truncate(webp.len() - 3);
+        assert_eq!(strip_metadata(&webp, "image/webp"), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::Cursor;
+
+use matroska::{Matroska, Settings, Tracktype};
+use mp4::{MediaType, Mp4Reader, TrackType};
+
+/// What the container headers tell about a video or audio file.
+pub(super) struct AvInfo {
+    pub(super) duration: Option<f64>,
+    pub(super) width: Option<u32>,
+    pub(super) height: Option<u32>,
+    pub(super) codec: String,
+}
+
+pub(super) enum ProbeError {
+    Malformed,
+    UnsupportedCodec,
+}
+
+const WEBM_CODECS: &[&str] = &["vp8", "vp9", "av1", "opus", "vorbis"];
+const MP4_CODECS: &[&str] = &["h264", "vp9", "aac"];
+const MP3_CODECS: &[&str] = &["mp3"];
+
+pub(super) fn is_audio_or_video(mime_type: &str) -> bool {
+    matches!(mime_type, "video/webm" | "video/mp4" | "audio/mpeg")
+}
+
+/// The codecs [probe] lets through in a container.
+pub(super) fn accepted_codecs(mime_type: &str) -> &'static [&'static str] {
+    match mime_type {
+        "video/webm" => WEBM_CODECS,
+        "video/mp4" => MP4_CODECS,
+        "audio/mpeg" => MP3_CODECS,
+        _ => &[],
+    }
+}
+
+/// Probes off the async workers, handing the data back. The parsers read untrusted bytes, a
+/// panic in one of them means the file is malformed.
+pub(super) async fn probe_in_background(
+    data: Vec<u8>,
+    mime_type: &'static str,
+) -> Result<(Vec<u8>, AvInfo), ProbeError> {
+    tokio::task::spawn_blocking(move || probe(&data, mime_type).map(|info| (data, info)))
+        .await
+        .unwrap_or(Err(ProbeError::Malformed))
+}
+
+/// Reads the container headers without decoding any frames.
+pub(super) fn probe(data: &[u8], mime_type: &str) -> Result<AvInfo, ProbeError> {
+    match mime_type {
+        "video/webm" => probe_webm(data),
+        "video/mp4" => probe_mp4(data),
+        "audio/mpeg" => probe_mp3(data),
+        _ => Err(ProbeError::UnsupportedCodec),
+    }
+}
+
+fn probe_webm(data: &[u8]) -> Result<AvInfo, ProbeError> {
+    let webm = Matroska::open(Cursor::new(data)).map_err(|_| ProbeError::Malformed)?;
+    let mut info = AvInfo {
+        duration: webm.info.duration.map(|d| d.as_secs_f64()),
+        width: None,
+        height: None,
+        codec: String::new(),
+    };
+    let mut codecs = Vec::new();
+    for track in &webm.tracks {
+        if !matches!(track.tracktype, Tracktype::Video | Tracktype::Audio) {
+            continue; // subtitles and the like carry no playable media
+        }
+        // ebml strings may be padded with trailing zero bytes
+        let codec = match track.codec_id.trim_end_matches('\0') {
+            "V_VP8" => "vp8",
+            "V_VP9" => "vp9",
+            "V_AV1" => "av1",
+            "A_OPUS" => "opus",
+            "A_VORBIS" => "vorbis",
+            _ => return Err(ProbeError::UnsupportedCodec),
+        };
+        if let Settings::Video(video) = &track.settings {
+            info.width = u32::try_from(video.pixel_width).ok();
+            info.height = u32::try_from(video.pixel_height).ok();
+        }
+        codecs.push(codec);
+    }
+    info.codec = joined_codecs(codecs)?;
+    Ok(info)
+}
+
+fn probe_mp4(data: &[u8]) -> Result<AvInfo, ProbeError> {
+    let mp4 = Mp4Reader::read_header(Cursor::new(data), data.len() as u64)
+        .map_err(|_| ProbeError::Malformed)?;
+    let mut info = AvInfo {
+        duration: Some(mp4.duration().as_secs_f64()),
+        width: None,
+        height: None,
+        codec: String::new(),
+    };
+    let mut tracks: Vec<_> = mp4.tracks().values().collect();
+    tracks.sort_by_key(|track| track.track_id());
+    let mut codecs = Vec::new();
+    for track in tracks {
+        let track_type = match track.track_type() {
+            Ok(track_type @ (TrackType::Video | TrackType::Audio)) => track_type,
+            _ => continue,
+        };
+        let codec = match track.media_type() {
+            Ok(MediaType::H264) => "h264",
+            Ok(MediaType::VP9) => "vp9",
+            Ok(MediaType::AAC) => "aac",
+            _ => return Err(ProbeError::UnsupportedCodec),
+        };
+        if let TrackType::Video = track_type {
+            info.width = Some(track.width().into());
+            info.height = Some(track.height().into());
+        }
+        codecs.push(codec);
+    }
+    info.codec = joined_codecs(codecs)?;
+    Ok(info)
+}
+
+fn probe_mp3(data: &[u8]) -> Result<AvInfo, ProbeError> {
+    let duration =
+        mp3_duration::from_read(&mut Cursor::new(data)).map_err(|_| ProbeError::Malformed)?;
+    Ok(AvInfo {
+        duration: Some(duration.as_secs_f64()),
+        width: None,
+        height: None,
+        codec: "mp3".to_owned(),
+    })
+}
+
+/// Names the codecs like `vp9+opus`, a container without any playable track is refused.
+fn joined_codecs(codecs: Vec<&str>) -> Result<String, ProbeError> {
+    if codecs.is_empty() {
+        return Err(ProbeError::Malformed);
+    }
+    Ok(codecs.join("+"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The start of a file as the type is detected from, followed by garbage or nothing.
+    const TRUNCATED: &[(&str, &[u8])] = &[
+        (
+            "video/webm",
+            &[0x1a, 0x45, 0xdf, 0xa3, 0x9f, 0x42, 0x86, 0x81],
+        ),
+        (
+            "video/webm",
+            &[
+                0x1a, 0x45, 0xdf, 0xa3, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        ),
+        ("video/mp4", b"\0\0\0\x18ftypisom\0\0\x02\0isomiso2"),
+        (
+            "video/mp4",
+            b"\0\0\0\x18ftypisom\0\0\x02\0isomiso2\xff\xff\xff\xffmoov",
+        ),
+        ("audio/mpeg", b"ID3\x04\0\0\0\0\x10\0"),
+        ("audio/mpeg", &[0xff, 0xfb, 0x90, 0x64, 0x00]),
+    ];
+
+    /// A segment whose element sizes point past the data, the matroska parser panics on it.
+    const PANICKING_WEBM: &[u8] = &[
+        26, 69, 223, 163, 128, 24, 83, 128, 103, 255, 183, 48, 34, 54, 30, 68, 46, 190, 13, 93, 59,
+        227, 112, 216, 211, 34, 182, 243, 232, 189, 74, 162, 201, 228, 31, 161, 20, 76, 190,
+    ];
+
+    #[tokio::test]
+    async fn refuses_containers_the_parser_panics_on() {
+        let probed = probe_in_background(PANICKING_WEBM.to_vec(), "video/webm").await;
+        assert!(matches!(probed, Err(ProbeError::Malformed)));
+    }
+
+    #[tokio::test]
+    async fn refuses_truncated_containers() {
+        for (mime_type, data) in TRUNCATED {
+            let probed = probe_in_background(data.to_vec(), mime_type).await;
+            assert!(
+                matches!(probed, Err(ProbeError::Malformed)),
+                "{} {:x?} should be malformed",
+                mime_type,
+                data
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_garbage() {
+        let garbage: Vec<u8> = (0..4096_u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        for mime_type in ["video/webm", "video/mp4", "audio/mpeg"] {
+            let probed = probe_in_background(garbage.clone(), mime_type).await;
+            assert!(probed.is_err(), "{} garbage should be refused", mime_type);
+        }
+    }
+}
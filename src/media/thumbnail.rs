@@ -0,0 +1,50 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use super::metadata;
+
+/// Longest edge of a generated thumbnail in pixels.
+const THUMBNAIL_MAX_EDGE: u32 = 250;
+
+pub(super) struct Thumbnail {
+    pub(super) data: Vec<u8>,
+    pub(super) mime_type: &'static str,
+}
+
+pub(super) struct ImageInfo {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) thumbnail: Thumbnail,
+}
+
+/// Decodes an image (the first frame for animations) and renders a downscaled thumbnail.
+pub(super) fn generate_thumbnail(data: &[u8]) -> Option<ImageInfo> {
+    let image = metadata::decode_oriented(data)?;
+    let (width, height) = image.dimensions();
+    let scaled = if width > THUMBNAIL_MAX_EDGE || height > THUMBNAIL_MAX_EDGE {
+        image.thumbnail(THUMBNAIL_MAX_EDGE, THUMBNAIL_MAX_EDGE)
+    } else {
+        image
+    };
+    // jpeg cannot carry transparency, keep png for those
+    let (scaled, format, mime_type) = if scaled.color().has_alpha() {
+        (scaled, ImageFormat::Png, "image/png")
+    } else {
+        (
+            DynamicImage::ImageRgb8(scaled.to_rgb8()),
+            ImageFormat::Jpeg,
+            "image/jpeg",
+        )
+    };
+    let mut encoded = Cursor::new(Vec::new());
+    scaled.write_to(&mut encoded, format).ok()?;
+    Some(ImageInfo {
+        width,
+        height,
+        thumbnail: Thumbnail {
+            data: encoded.into_inner(),
+            mime_type,
+        },
+    })
+}
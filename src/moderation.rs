@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+
+mod moderation_use_case;
+
+pub use moderation_use_case::{
+    ModerationError, ModerationPersistence, ModerationUseCase, domain_policy_for, is_hash_banned,
+    moderation_use_case,
+};
+
+/// A file banned on every board, identified by the SHA-256 of its content.
+pub struct BannedHash {
+    pub hash: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct HashImport {
+    pub imported: u64,
+    pub invalid: usize,
+}
+
+pub fn normalize_hash(hash: &str) -> Option<String> {
+    let hash = hash.trim().to_ascii_lowercase();
+    let is_sha256 = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    is_sha256.then_some(hash)
+}
+
+/// How federation treats a remote domain, `*.example.com` also covers its subdomains.
+pub struct DomainPolicy {
+    pub domain: String,
+    pub policy: Policy,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    /// Nothing is accepted from the domain and nothing is sent to it.
+    Block,
+    /// Posts from the domain are taken without their attachments, the only silence level.
+    RejectMedia,
+    /// The default, also lifts a wildcard policy for a single subdomain.
+    Accept,
+}
+
+impl Policy {
+    /// The name used by the admin api and stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Policy::Block => "block",
+            Policy::RejectMedia => "reject_media",
+            Policy::Accept => "accept",
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "block" => Ok(Policy::Block),
+            "reject_media" => Ok(Policy::RejectMedia),
+            "accept" => Ok(Policy::Accept),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Lower-cases a host name or `*.` wildcard, rejecting urls and anything else.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    let host = domain.strip_prefix("*.").unwrap_or(&domain);
+    let is_host = !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    is_host.then_some(domain)
+}
+
+/// The entries that could apply to a host, the most specific first.
+fn domain_patterns(host: &str) -> Vec<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let mut patterns = vec![host.clone()];
+    let mut rest = host.as_str();
+    loop {
+        patterns.push(format!("*.{}", rest));
+        match rest.split_once('.') {
+            Some((_, parent)) => rest = parent,
+            None => break,
+        }
+    }
+    patterns
+}
+
+/// Picks the policy of the most specific entry that applies to a host.
+fn policy_for(host: &str, policies: &[DomainPolicy]) -> Policy {
+    domain_patterns(host)
+        .iter()
+        .find_map(|pattern| {
+            policies
+                .iter()
+                .find(|policy| &policy.domain == pattern)
+                .map(|policy| policy.policy)
+        })
+        .unwrap_or(Policy::Accept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(domain: &str, policy: Policy) -> DomainPolicy {
+        DomainPolicy {
+            domain: domain.to_owned(),
+            policy,
+            reason: String::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn normalizes_domains() {
+        assert_eq!(
+            normalize_domain(" Example.COM. ").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            normalize_domain("*.Example.com").as_deref(),
+            Some("*.example.com")
+        );
+        assert_eq!(normalize_domain("https://example.com"), None);
+        assert_eq!(normalize_domain("example..com"), None);
+        assert_eq!(normalize_domain("*."), None);
+    }
+
+    #[test]
+    fn lists_patterns_most_specific_first() {
+        assert_eq!(
+            domain_patterns("A.Example.com."),
+            ["a.example.com", "*.a.example.com", "*.example.com", "*.com"]
+        );
+    }
+
+    #[test]
+    fn matches_the_exact_host() {
+        let policies = [entry("example.com", Policy::Block)];
+        assert_eq!(policy_for("example.com", &policies), Policy::Block);
+        assert_eq!(policy_for("EXAMPLE.com.", &policies), Policy::Block);
+        assert_eq!(policy_for("a.example.com", &policies), Policy::Accept);
+        assert_eq!(policy_for("notexample.com", &policies), Policy::Accept);
+    }
+
+    #[test]
+    fn wildcards_cover_the_parent_and_its_subdomains() {
+        let policies = [entry("*.example.com", Policy::RejectMedia)];
+        assert_eq!(policy_for("example.com", &policies), Policy::RejectMedia);
+        assert_eq!(
+            policy_for("a.b.example.com", &policies),
+            Policy::RejectMedia
+        );
+        assert_eq!(policy_for("example.org", &policies), Policy::Accept);
+    }
+
+    #[test]
+    fn the_most_specific_entry_wins() {
+        let policies = [
+            entry("*.example.com", Policy::Block),
+            entry("good.example.com", Policy::Accept),
+            entry("*.media.example.com", Policy::RejectMedia),
+        ];
+        assert_eq!(policy_for("good.example.com", &policies), Policy::Accept);
+        assert_eq!(policy_for("bad.example.com", &policies), Policy::Block);
+        assert_eq!(
+            policy_for("a.media.example.com", &policies),
+            Policy::RejectMedia
+        );
+    }
+
+    #[test]
+    fn policies_round_trip_through_their_names() {
+        for policy in [Policy::Block, Policy::RejectMedia, Policy::Accept] {
+            assert_eq!(policy.as_str().parse(), Ok(policy));
+        }
+        assert_eq!("Block".parse::<Policy>(), Err(()));
+    }
+}
@@ -0,0 +1,209 @@
+use super::{
+    BannedHash, DomainPolicy, HashImport, Policy, domain_patterns, normalize_domain,
+    normalize_hash, policy_for,
+};
+
+pub enum ModerationError {
+    NotFound,
+    DbError,
+    InvalidHash,
+    InvalidDomain,
+}
+
+pub trait ModerationPersistence {
+    fn find_banned_hash(
+        &self,
+        hash: &str,
+    ) -> impl Future<Output = Result<BannedHash, ModerationError>> + Send;
+
+    fn find_all_banned_hashes(
+        &self,
+    ) -> impl Future<Output = Result<Vec<BannedHash>, ModerationError>> + Send;
+
+    /// Inserts all hashes not banned yet, returning how many were new.
+    fn insert_banned_hashes(
+        &self,
+        hashes: &[String],
+        reason: &str,
+    ) -> impl Future<Output = Result<u64, ModerationError>> + Send;
+
+    fn delete_banned_hash(
+        &self,
+        hash: &str,
+    ) -> impl Future<Output = Result<(), ModerationError>> + Send;
+
+    fn find_domain_policies(
+        &self,
+        domains: &[String],
+    ) -> impl Future<Output = Result<Vec<DomainPolicy>, ModerationError>> + Send;
+
+    fn find_all_domain_policies(
+        &self,
+    ) -> impl Future<Output = Result<Vec<DomainPolicy>, ModerationError>> + Send;
+
+    /// Sets the policy of a domain, replacing the one it had.
+    fn upsert_domain_policy(
+        &self,
+        domain: &str,
+        policy: Policy,
+        reason: &str,
+    ) -> impl Future<Output = Result<DomainPolicy, ModerationError>> + Send;
+
+    fn delete_domain_policy(
+        &self,
+        domain: &str,
+    ) -> impl Future<Output = Result<(), ModerationError>> + Send;
+}
+
+pub trait ModerationUseCase {
+    fn get_banned_hashes(
+        &self,
+    ) -> impl Future<Output = Result<Vec<BannedHash>, ModerationError>> + Send;
+
+    /// Returns the ban and whether it is new, an existing ban keeps its reason.
+    fn ban_hash(
+        &self,
+        hash: &str,
+        reason: &str,
+    ) -> impl Future<Output = Result<(BannedHash, bool), ModerationError>> + Send;
+
+    fn unban_hash(&self, hash: &str) -> impl Future<Output = Result<(), ModerationError>> + Send;
+
+    /// Bans every hash of a newline separated list, skipping blank and `#` comment lines.
+    fn import_banned_hashes(
+        &self,
+        hash_list: &str,
+        reason: &str,
+    ) -> impl Future<Output = Result<HashImport, ModerationError>> + Send;
+
+    fn get_domain_policies(
+        &self,
+    ) -> impl Future<Output = Result<Vec<DomainPolicy>, ModerationError>> + Send;
+
+    fn set_domain_policy(
+        &self,
+        domain: &str,
+        policy: Policy,
+        reason: &str,
+    ) -> impl Future<Output = Result<DomainPolicy, ModerationError>> + Send;
+
+    fn remove_domain_policy(
+        &self,
+        domain: &str,
+    ) -> impl Future<Output = Result<(), ModerationError>> + Send;
+
+    fn get_policy_for_host(
+        &self,
+        host: &str,
+    ) -> impl Future<Output = Result<Policy, ModerationError>> + Send;
+}
+
+pub fn moderation_use_case(
+    persistence: impl ModerationPersistence + Sync,
+) -> impl ModerationUseCase {
+    ModerationUseCaseImpl { persistence }
+}
+
+pub async fn is_hash_banned(
+    persistence: &(impl ModerationPersistence + Sync),
+    hash: &str,
+) -> Result<bool, ModerationError> {
+    match persistence.find_banned_hash(hash).await {
+        Ok(_) => Ok(true),
+        Err(ModerationError::NotFound) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Shared by the use cases that talk to other servers, the most specific entry wins.
+pub async fn domain_policy_for(
+    persistence: &(impl ModerationPersistence + Sync),
+    host: &str,
+) -> Result<Policy, ModerationError> {
+    let policies = persistence
+        .find_domain_policies(&domain_patterns(host))
+        .await?;
+    Ok(policy_for(host, &policies))
+}
+
+struct ModerationUseCaseImpl<T>
+where
+    T: ModerationPersistence,
+{
+    persistence: T,
+}
+
+impl<T: ModerationPersistence + Sync> ModerationUseCase for ModerationUseCaseImpl<T> {
+    async fn get_banned_hashes(&self) -> Result<Vec<BannedHash>, ModerationError> {
+        self.persistence.find_all_banned_hashes().await
+    }
+
+    async fn ban_hash(
+        &self,
+        hash: &str,
+        reason: &str,
+    ) -> Result<(BannedHash, bool), ModerationError> {
+        let hash = normalize_hash(hash).ok_or(ModerationError::InvalidHash)?;
+        let inserted = self
+            .persistence
+            .insert_banned_hashes(std::slice::from_ref(&hash), reason)
+            .await?;
+        let banned = self.persistence.find_banned_hash(&hash).await?;
+        Ok((banned, inserted > 0))
+    }
+
+    async fn unban_hash(&self, hash: &str) -> Result<(), ModerationError> {
+        let hash = normalize_hash(hash).ok_or(ModerationError::InvalidHash)?;
+        self.persistence.delete_banned_hash(&hash).await
+    }
+
+    async fn import_banned_hashes(
+        &self,
+        hash_list: &str,
+        reason: &str,
+    ) -> Result<HashImport, ModerationError> {
+        let mut hashes = Vec::new();
+        let mut invalid = 0;
+        for line in hash_list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // also accept sha256sum output, the hash comes first
+            let first_token = line.split_whitespace().next().unwrap_or_default();
+            match normalize_hash(first_token) {
+                Some(hash) => hashes.push(hash),
+                None => invalid += 1,
+            }
+        }
+        let imported = self
+            .persistence
+            .insert_banned_hashes(&hashes, reason)
+            .await?;
+        Ok(HashImport { imported, invalid })
+    }
+
+    async fn get_domain_policies(&self) -> Result<Vec<DomainPolicy>, ModerationError> {
+        self.persistence.find_all_domain_policies().await
+    }
+
+    async fn set_domain_policy(
+        &self,
+        domain: &str,
+        policy: Policy,
+        reason: &str,
+    ) -> Result<DomainPolicy, ModerationError> {
+        let domain = normalize_domain(domain).ok_or(ModerationError::InvalidDomain)?;
+        self.persistence
+            .upsert_domain_policy(&domain, policy, reason)
+            .await
+    }
+
+    async fn remove_domain_policy(&self, domain: &str) -> Result<(), ModerationError> {
+        let domain = normalize_domain(domain).ok_or(ModerationError::InvalidDomain)?;
+        self.persistence.delete_domain_policy(&domain).await
+    }
+
+    async fn get_policy_for_host(&self, host: &str) -> Result<Policy, ModerationError> {
+        domain_policy_for(&self.persistence, host).await
+    }
+}
@@ -1,10 +1,13 @@
 mod post;
 mod thread_use_case;
 
+pub(crate) use thread_use_case::{check_banned, check_duplicates};
+
 use uuid::Uuid;
 
 pub use post::{
-    Post, PostError, PostUseCase, Posts, extract_post_by_id, extract_posts, post_use_case,
+    Attachment, Post, PostError, PostUseCase, Posts, Reaction, ReactionKind, RemoteOrigin,
+    extract_post_by_id, extract_posts, post_use_case,
 };
 pub use thread_use_case::{
     ThreadCreation, ThreadError, ThreadPersistence, ThreadUseCase, thread_use_case,
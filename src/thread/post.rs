@@ -1,5 +1,7 @@
 mod post_use_case;
 
+use chrono::{DateTime, Utc};
+
 use crate::thread::Thread;
 use sqlx::types::Uuid;
 
@@ -24,6 +26,53 @@ pub struct Post {
     pub subject: Option<String>,
     pub content: Option<String>,
     pub media_url: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub spoiler: bool,
+    /// Set for replies that came in from the fediverse, their content is sanitized html.
+    pub remote: Option<RemoteOrigin>,
+    /// Missing for posts written before the time was kept.
+    pub published: Option<DateTime<Utc>>,
+    pub edited: Option<DateTime<Utc>>,
+    /// A deleted post keeps its place in the thread, without anything it said.
+    pub deleted: Option<DateTime<Utc>>,
+    pub reactions: Vec<Reaction>,
+}
+
+/// A like or boost from the fediverse, kept so it can be undone.
+#[derive(Clone)]
+pub struct Reaction {
+    pub kind: ReactionKind,
+    pub actor_id: String,
+    pub activity_id: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReactionKind {
+    Like,
+    Announce,
+}
+
+/// Where a federated post was written.
+#[derive(Clone)]
+pub struct RemoteOrigin {
+    pub actor_id: String,
+    pub object_id: String,
+    /// The `Create` the post arrived in, serialized json, passed on as is when it is announced.
+    pub activity: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Attachment {
+    /// SHA-256 of the uploaded file, missing for attachments stored before hashing.
+    pub hash: Option<String>,
+    pub url: String,
+    pub mime_type: String,
+    pub size: usize,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub thumbnail_url: Option<String>,
+    pub duration: Option<f64>,
+    pub codec: Option<String>,
 }
 
 pub struct Posts {
@@ -1,40 +1,291 @@
-use super::Post;
+use super::{Post, Reaction};
+use crate::board::Board;
+use crate::federation::Publisher;
+use crate::moderation::ModerationPersistence;
 use crate::thread::Thread;
+use crate::thread::ThreadError;
 use crate::thread::ThreadPersistence;
+use crate::thread::{check_banned, check_duplicates};
+use chrono::Utc;
+use uuid::Uuid;
 
 pub enum PostError {
     DbError,
+    DuplicateFile(Uuid), // id of the thread already holding the file
+    BannedFile,
+    NotFound,
+    NotAuthor,
 }
 
 pub trait PostUseCase {
+    /// The checks of [PostUseCase::post_into_thread], run before attachments are stored.
+    fn validate_post(
+        &self,
+        board: &Board,
+        new_post: &Post,
+    ) -> impl Future<Output = Result<(), PostError>> + Send;
+
     fn post_into_thread(
         &self,
+        board: &Board,
         thread: Thread,
         new_post: Post,
     ) -> impl Future<Output = Result<Post, PostError>> + Send;
+
+    /// Replaces a federated post with a new version from the same author.
+    fn update_remote_post(
+        &self,
+        board: &Board,
+        thread: Thread,
+        updated: Post,
+    ) -> impl Future<Output = Result<Post, PostError>> + Send;
+
+    /// Blanks a federated post, only its author may delete it.
+    fn delete_remote_post(
+        &self,
+        thread: Thread,
+        object_id: &str,
+        actor_id: &str,
+    ) -> impl Future<Output = Result<(), PostError>> + Send;
+
+    /// Adding the same reaction twice keeps one.
+    fn add_reaction(
+        &self,
+        thread: Thread,
+        post_id: Uuid,
+        reaction: Reaction,
+    ) -> impl Future<Output = Result<(), PostError>> + Send;
+
+    /// Removes the reaction made by the activity, if the actor made it.
+    fn remove_reaction(
+        &self,
+        thread: Thread,
+        activity_id: &str,
+        actor_id: &str,
+    ) -> impl Future<Output = Result<(), PostError>> + Send;
 }
 
-pub fn post_use_case(thread_persistence: impl ThreadPersistence + Sync) -> impl PostUseCase {
-    PostUseCaseImpl { thread_persistence }
+pub fn post_use_case(
+    thread_persistence: impl ThreadPersistence + Sync,
+    moderation_persistence: impl ModerationPersistence + Sync,
+    publisher: impl Publisher + Sync,
+) -> impl PostUseCase {
+    PostUseCaseImpl {
+        thread_persistence,
+        moderation_persistence,
+        publisher,
+    }
 }
 
-pub(crate) struct PostUseCaseImpl<T>
+pub(crate) struct PostUseCaseImpl<T, M, P>
 where
     T: ThreadPersistence,
+    M: ModerationPersistence,
+    P: Publisher,
 {
     pub(crate) thread_persistence: T,
+    pub(crate) moderation_persistence: M,
+    pub(crate) publisher: P,
 }
 
-impl<T: ThreadPersistence + Sync> PostUseCase for PostUseCaseImpl<T> {
-    async fn post_into_thread(&self, thread: Thread, new_post: Post) -> Result<Post, PostError> {
-        let updated = self.thread_persistence.insert_post(&thread, new_post).await;
+impl<T: ThreadPersistence + Sync, M: ModerationPersistence + Sync, P: Publisher + Sync> PostUseCase
+    for PostUseCaseImpl<T, M, P>
+{
+    async fn validate_post(&self, board: &Board, new_post: &Post) -> Result<(), PostError> {
+        check_banned(
+            &self.moderation_persistence,
+            &new_post.attachments,
+            new_post.media_url.as_deref(),
+        )
+        .await
+        .map_err(to_post_error)?;
+        if board.settings.reject_duplicate_files {
+            check_duplicates(&self.thread_persistence, board, &new_post.attachments)
+                .await
+                .map_err(to_post_error)?;
+        }
+        Ok(())
+    }
+
+    async fn post_into_thread(
+        &self,
+        board: &Board,
+        thread: Thread,
+        mut new_post: Post,
+    ) -> Result<Post, PostError> {
+        self.validate_post(board, &new_post).await?;
+        new_post.spoiler &= board.settings.spoilers_enabled;
+        let updated = self
+            .thread_persistence
+            .insert_post(board, &thread, new_post)
+            .await;
         let mut updated: Thread = match updated {
             Ok(updated_thread) => updated_thread,
-            Err(_) => return Err(PostError::DbError),
+            Err(err) => return Err(to_post_error(err)),
+        };
+        let post = match updated.posts.posts.pop() {
+            Some(p) => p,
+            None => return Err(PostError::DbError),
         };
-        match updated.posts.posts.pop() {
-            Some(p) => Ok(p),
-            None => Err(PostError::DbError),
+        self.publisher.publish_post(board, &updated, &post).await;
+        Ok(post)
+    }
+
+    async fn update_remote_post(
+        &self,
+        board: &Board,
+        thread: Thread,
+        mut updated: Post,
+    ) -> Result<Post, PostError> {
+        let updated_remote = updated.remote.clone().ok_or(PostError::NotAuthor)?;
+        let original = find_remote_post(&thread, &updated_remote.object_id)?;
+        check_author(original, &updated_remote.actor_id)?;
+        if original.deleted.is_some() {
+            return Err(PostError::NotFound);
+        }
+        updated.id = original.id;
+        updated.edited = Some(Utc::now());
+        updated.spoiler &= board.settings.spoilers_enabled;
+        let thread = self
+            .thread_persistence
+            .update_post(&thread.thread_id, |stored| {
+                // reactions may have arrived since the thread was read
+                let original = find_remote_post(stored, &updated_remote.object_id).ok()?;
+                if original.deleted.is_some() {
+                    return None;
+                }
+                Some(Post {
+                    published: original.published,
+                    reactions: original.reactions.clone(),
+                    remote: original.remote.clone(),
+                    ..updated
+                })
+            })
+            .await
+            .map_err(to_post_error)?;
+        let post = find_remote_post(&thread, &updated_remote.object_id)?;
+        match post.deleted {
+            Some(_) => Err(PostError::NotFound),
+            None => Ok(post.clone()),
+        }
+    }
+
+    async fn delete_remote_post(
+        &self,
+        thread: Thread,
+        object_id: &str,
+        actor_id: &str,
+    ) -> Result<(), PostError> {
+        let original = find_remote_post(&thread, object_id)?;
+        check_author(original, actor_id)?;
+        if original.deleted.is_some() {
+            return Ok(());
         }
+        self.thread_persistence
+            .update_post(&thread.thread_id, |stored| {
+                let original = find_remote_post(stored, object_id).ok()?;
+                if original.deleted.is_some() {
+                    return None;
+                }
+                Some(Post {
+                    name: None,
+                    subject: None,
+                    content: None,
+                    media_url: None,
+                    attachments: Vec::new(),
+                    deleted: Some(Utc::now()),
+                    ..original.clone()
+                })
+            })
+            .await
+            .map_err(to_post_error)?;
+        Ok(())
+    }
+
+    async fn add_reaction(
+        &self,
+        thread: Thread,
+        post_id: Uuid,
+        reaction: Reaction,
+    ) -> Result<(), PostError> {
+        if !thread.posts.posts.iter().any(|post| post.id == post_id) {
+            return Err(PostError::NotFound);
+        }
+        self.thread_persistence
+            .update_post(&thread.thread_id, |stored| {
+                let mut post = stored
+                    .posts
+                    .posts
+                    .iter()
+                    .find(|post| post.id == post_id)?
+                    .clone();
+                // a server may resend, an actor likes or boosts a post once
+                if post.reactions.iter().any(|existing| {
+                    existing.activity_id == reaction.activity_id
+                        || (existing.actor_id == reaction.actor_id
+                            && existing.kind == reaction.kind)
+                }) {
+                    return None;
+                }
+                post.reactions.push(reaction);
+                Some(post)
+            })
+            .await
+            .map_err(to_post_error)?;
+        Ok(())
+    }
+
+    async fn remove_reaction(
+        &self,
+        thread: Thread,
+        activity_id: &str,
+        actor_id: &str,
+    ) -> Result<(), PostError> {
+        let is_undone = |reaction: &Reaction| {
+            reaction.activity_id == activity_id && reaction.actor_id == actor_id
+        };
+        self.thread_persistence
+            .update_post(&thread.thread_id, |stored| {
+                let mut post = stored
+                    .posts
+                    .posts
+                    .iter()
+                    .find(|post| post.reactions.iter().any(is_undone))?
+                    .clone();
+                post.reactions.retain(|reaction| !is_undone(reaction));
+                Some(post)
+            })
+            .await
+            .map_err(to_post_error)?;
+        Ok(())
+    }
+}
+
+fn find_remote_post<'a>(thread: &'a Thread, object_id: &str) -> Result<&'a Post, PostError> {
+    thread
+        .posts
+        .posts
+        .iter()
+        .find(|post| {
+            post.remote
+                .as_ref()
+                .is_some_and(|remote| remote.object_id == object_id)
+        })
+        .ok_or(PostError::NotFound)
+}
+
+/// Federated posts can only be changed by the actor that wrote them.
+fn check_author(post: &Post, actor_id: &str) -> Result<(), PostError> {
+    match &post.remote {
+        Some(remote) if remote.actor_id == actor_id => Ok(()),
+        _ => Err(PostError::NotAuthor),
+    }
+}
+
+fn to_post_error(err: ThreadError) -> PostError {
+    match err {
+        ThreadError::DuplicateFile(original) => PostError::DuplicateFile(original),
+        ThreadError::BannedFile => PostError::BannedFile,
+        _ => PostError::DbError,
     }
 }
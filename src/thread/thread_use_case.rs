@@ -1,7 +1,9 @@
 use super::Thread;
 use crate::board::Board;
 use crate::board::BoardUseCase;
-use crate::thread::Post;
+use crate::federation::Publisher;
+use crate::moderation::{ModerationPersistence, is_hash_banned};
+use crate::thread::{Attachment, Post, RemoteOrigin};
 use uuid::Uuid;
 
 pub struct ThreadCreation {
@@ -9,12 +11,19 @@ pub struct ThreadCreation {
     pub subject: Option<String>,
     pub content: Option<String>,
     pub media_url: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub spoiler: bool,
+    pub remote: Option<RemoteOrigin>,
 }
 
 pub enum ThreadError {
     IdError,
     DbError,
     NotFound,
+    DuplicateFile(Uuid), // id of the thread already holding the file
+    BannedFile,
+    /// Threads of a mirrored board can only come from the group it mirrors.
+    MirroredBoard,
 }
 
 pub trait ThreadPersistence {
@@ -28,17 +37,61 @@ pub trait ThreadPersistence {
         board: &Board,
     ) -> impl Future<Output = Result<Vec<Thread>, ThreadError>> + Send;
 
+    /// Posts written on this instance, federated replies are left out.
+    fn count_local_posts(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<i64, ThreadError>> + Send;
+
+    /// Newest first, the threads only hold their opening post.
+    fn find_local_posts(
+        &self,
+        board: &Board,
+        limit: i64,
+        offset: i64,
+    ) -> impl Future<Output = Result<Vec<(Thread, Post)>, ThreadError>> + Send;
+
+    fn find_thread_by_media_hash(
+        &self,
+        board: &Board,
+        hash: &str,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    fn find_thread_by_remote_object(
+        &self,
+        board: &Board,
+        object_id: &str,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    fn find_thread_by_reaction(
+        &self,
+        board: &Board,
+        activity_id: &str,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    /// Duplicate files are checked in the same transaction as the insert.
     fn insert_thread(
         &self,
-        board: Board,
+        board: &Board,
         thread_creation: ThreadCreation,
     ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
 
+    /// Checks for duplicate files like [ThreadPersistence::insert_thread].
     fn insert_post(
         &self,
+        board: &Board,
         thread: &Thread,
         post: Post,
     ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    /// Replaces the post with the id of the one `change` returns, nothing is written for `None`.
+    /// `change` sees the thread as stored, locked until the update is done, so concurrent
+    /// deliveries to the same thread do not overwrite each other.
+    fn update_post(
+        &self,
+        thread_id: &Uuid,
+        change: impl FnOnce(&Thread) -> Option<Post> + Send,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
 }
 
 pub trait ThreadUseCase {
@@ -54,25 +107,71 @@ pub trait ThreadUseCase {
         board_use_case: impl BoardUseCase + Send,
     ) -> impl Future<Output = Result<Vec<Thread>, ThreadError>> + Send;
 
+    /// The checks of [ThreadUseCase::create_thread], run before attachments are stored.
+    fn validate_thread(
+        &self,
+        board: &Board,
+        thread_creation: &ThreadCreation,
+    ) -> impl Future<Output = Result<(), ThreadError>> + Send;
+
     fn create_thread(
         &self,
         board: Board,
         thread_creation: ThreadCreation,
     ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    fn count_local_posts(
+        &self,
+        board: &Board,
+    ) -> impl Future<Output = Result<i64, ThreadError>> + Send;
+
+    /// A page of the board's own posts, newest first, see [ThreadPersistence::find_local_posts].
+    fn get_local_posts(
+        &self,
+        board: &Board,
+        limit: i64,
+        offset: i64,
+    ) -> impl Future<Output = Result<Vec<(Thread, Post)>, ThreadError>> + Send;
+
+    fn get_thread_by_remote_object(
+        &self,
+        board: &Board,
+        object_id: &str,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
+
+    fn get_thread_by_reaction(
+        &self,
+        board: &Board,
+        activity_id: &str,
+    ) -> impl Future<Output = Result<Thread, ThreadError>> + Send;
 }
 
-pub fn thread_use_case(persistence: impl ThreadPersistence + Sync) -> impl ThreadUseCase {
-    ThreadUseCaseImpl { persistence }
+pub fn thread_use_case(
+    persistence: impl ThreadPersistence + Sync,
+    moderation_persistence: impl ModerationPersistence + Sync,
+    publisher: impl Publisher + Sync,
+) -> impl ThreadUseCase {
+    ThreadUseCaseImpl {
+        persistence,
+        moderation_persistence,
+        publisher,
+    }
 }
 
-struct ThreadUseCaseImpl<T>
+struct ThreadUseCaseImpl<T, M, P>
 where
     T: ThreadPersistence,
+    M: ModerationPersistence,
+    P: Publisher,
 {
     persistence: T,
+    moderation_persistence: M,
+    publisher: P,
 }
 
-impl<T: ThreadPersistence + Sync> ThreadUseCase for ThreadUseCaseImpl<T> {
+impl<T: ThreadPersistence + Sync, M: ModerationPersistence + Sync, P: Publisher + Sync>
+    ThreadUseCase for ThreadUseCaseImpl<T, M, P>
+{
     async fn get_thread_by_id(
         &self,
         thread_id: &str,
@@ -87,25 +186,131 @@ impl<T: ThreadPersistence + Sync> ThreadUseCase for ThreadUseCaseImpl<T> {
         self.persistence.find_thread_by_id(&thread_uuid).await
     }
 
-    fn get_threads_by_board(
+    async fn get_threads_by_board(
         &self,
         board_name: &str,
         board_use_case: impl BoardUseCase + Send,
-    ) -> impl Future<Output = Result<Vec<Thread>, ThreadError>> + Send {
-        async move {
-            let board: Board = match board_use_case.get_board_by_name(board_name).await {
-                Ok(board) => board,
-                Err(_) => return Err(ThreadError::DbError), // TODO
-            };
-            self.persistence.find_threads_by_board(&board).await
+    ) -> Result<Vec<Thread>, ThreadError> {
+        let board: Board = match board_use_case.get_board_by_name(board_name).await {
+            Ok(board) => board,
+            Err(_) => return Err(ThreadError::DbError), // TODO
+        };
+        self.persistence.find_threads_by_board(&board).await
+    }
+
+    async fn validate_thread(
+        &self,
+        board: &Board,
+        thread_creation: &ThreadCreation,
+    ) -> Result<(), ThreadError> {
+        if board.mirror.is_some() && thread_creation.remote.is_none() {
+            return Err(ThreadError::MirroredBoard);
+        }
+        check_banned(
+            &self.moderation_persistence,
+            &thread_creation.attachments,
+            thread_creation.media_url.as_deref(),
+        )
+        .await?;
+        if board.settings.reject_duplicate_files {
+            check_duplicates(&self.persistence, board, &thread_creation.attachments).await?;
         }
+        Ok(())
     }
 
     async fn create_thread(
         &self,
         board: Board,
-        thread_creation: ThreadCreation,
+        mut thread_creation: ThreadCreation,
     ) -> Result<Thread, ThreadError> {
-        self.persistence.insert_thread(board, thread_creation).await
+        self.validate_thread(&board, &thread_creation).await?;
+        thread_creation.spoiler &= board.settings.spoilers_enabled;
+        let thread = self
+            .persistence
+            .insert_thread(&board, thread_creation)
+            .await?;
+        self.publisher.publish_thread(&board, &thread).await;
+        Ok(thread)
+    }
+
+    async fn count_local_posts(&self, board: &Board) -> Result<i64, ThreadError> {
+        self.persistence.count_local_posts(board).await
+    }
+
+    async fn get_local_posts(
+        &self,
+        board: &Board,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(Thread, Post)>, ThreadError> {
+        self.persistence
+            .find_local_posts(board, limit, offset)
+            .await
+    }
+
+    async fn get_thread_by_remote_object(
+        &self,
+        board: &Board,
+        object_id: &str,
+    ) -> Result<Thread, ThreadError> {
+        self.persistence
+            .find_thread_by_remote_object(board, object_id)
+            .await
+    }
+
+    async fn get_thread_by_reaction(
+        &self,
+        board: &Board,
+        activity_id: &str,
+    ) -> Result<Thread, ThreadError> {
+        self.persistence
+            .find_thread_by_reaction(board, activity_id)
+            .await
+    }
+}
+
+/// Checks the hashes of the upload and of the stored file, which `media_url` may link.
+pub(crate) async fn check_banned(
+    moderation_persistence: &(impl ModerationPersistence + Sync),
+    attachments: &[Attachment],
+    media_url: Option<&str>,
+) -> Result<(), ThreadError> {
+    let uploaded = attachments.iter().filter_map(|a| a.hash.as_deref());
+    let stored = attachments
+        .iter()
+        .map(|a| a.url.as_str())
+        .chain(media_url)
+        .filter_map(stored_hash_of);
+    for hash in uploaded.chain(stored) {
+        match is_hash_banned(moderation_persistence, hash).await {
+            Ok(false) => continue,
+            Ok(true) => return Err(ThreadError::BannedFile),
+            Err(_) => return Err(ThreadError::DbError),
+        }
+    }
+    Ok(())
+}
+
+/// The content hash a stored file's url ends with, like `/api/files/{sha256}.png`.
+fn stored_hash_of(url: &str) -> Option<&str> {
+    let file_name = url.rsplit('/').next()?;
+    let (hash, _) = file_name.split_once('.')?;
+    let is_sha256 = hash.len() == 64 && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'));
+    is_sha256.then_some(hash)
+}
+
+/// Fails with the first thread on the board that already holds one of the attachments.
+pub(crate) async fn check_duplicates(
+    persistence: &(impl ThreadPersistence + Sync),
+    board: &Board,
+    attachments: &[Attachment],
+) -> Result<(), ThreadError> {
+    for hash in attachments.iter().filter_map(|a| a.hash.as_deref()) {
+        match persistence.find_thread_by_media_hash(board, hash).await {
+            Ok(original) => return Err(ThreadError::DuplicateFile(original.thread_id)),
+            Err(ThreadError::NotFound) => continue,
+            Err(err) => return Err(err),
+        }
     }
+    Ok(())
 }